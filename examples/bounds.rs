@@ -1,6 +1,20 @@
 use std::vec;
 
-use labcat::bounds::{BoundTransform, Bounds};
+use labcat::bounds::{BoundRepr, BoundReprs, BoundTransform, BoundTransformTrait, Bounds};
+use labcat::{f_, i_};
+
+// User-defined cube transform, which is strictly increasing over any range
+struct Cube;
+
+impl BoundTransformTrait for Cube {
+    fn transform(&self, x: f_) -> f_ {
+        x.powi(3)
+    }
+
+    fn inv_transform(&self, x: f_) -> f_ {
+        x.cbrt()
+    }
+}
 
 fn main() {
     // New bounds are constructed using builder notation. This struct also uses the type state pattern to ensure that only fully configured Bounds can be used.
@@ -13,22 +27,113 @@ fn main() {
     let manual_bounds = manual_bounds_cfg
         // Continuous and discrete bounds support Log, BiLog and Logistic transformations of the bounding values.
         .add_continuous_with_transform("d3", 0.95, 0.05, BoundTransform::Logistic)
-        // Currently, LABCAT lacks explicit handling of categorical and boolean values. These bounds are currently discretized based on the number of valid states (i.e., 3 categories are mapped to the interval (0, 3)) cast to a continuous axis.
+        // Categorical and boolean values are encoded by the interval of their state (i.e., 3 categories are mapped to the intervals (0, 1), (1, 2) and (2, 3)). These axes are not rotated or rescaled by LABCAT, but modelled with an overlap kernel and optimised by a discrete local search.
         .add_categorical("d4", vec!["c1", "c2", "c3"])
         .add_boolean("d5")
+        // Ordinal values are encoded by the index of their level, while quantized continuous values are rounded to the closest step when suggested.
+        .add_ordinal("d6", vec![0.1, 0.5, 2.0, 10.0])
+        .add_quantized_continuous("d7", 1.0, 0.0, 0.05)
         // Use the build command to finalize the Bounds struct
         .build();
 
     println!("{}", &manual_bounds);
-    let transformed_point = ndarray::array![1.0, 0.0, -2.0, 1.5, 0.5];
+    let transformed_point = ndarray::array![1.0, 0.0, -2.0, 1.5, 0.5, 2.0, 0.35];
     println!(
         "{} -> {}",
         &manual_bounds.repr(transformed_point.view()).unwrap(),
         &transformed_point
     );
 
-    assert!(manual_bounds.inside(ndarray::array![1.0, 0.0, 0.5, 1.0, 0.5].view()));
-    assert!(!manual_bounds.inside(ndarray::array![-1.0, -10.0, 1.0, 4.0, 3.0].view()));
+    assert!(manual_bounds.inside(ndarray::array![1.0, 0.0, 0.5, 1.0, 0.5, 3.0, 0.2].view()));
+    assert!(!manual_bounds.inside(ndarray::array![-1.0, -10.0, 1.0, 4.0, 3.0, 4.0, 1.5].view()));
+
+    // Bounds can be made conditional on the value of another bound. Inactive bounds are left out of the representation of a point and fixed at an imputed value.
+    let conditional_bounds = Bounds::new()
+        .add_categorical("optimizer", vec!["sgd", "adam"])
+        .add_continuous("momentum", 0.99, 0.0)
+        .add_condition(
+            "momentum",
+            "optimizer",
+            vec![BoundRepr::Categorical(("optimizer".into(), "sgd".into()))],
+        )
+        .build();
+
+    println!("{}", &conditional_bounds);
+    let adam_point = conditional_bounds.snap(ndarray::array![1.5, 0.9].view());
+    let adam_repr = conditional_bounds.repr(adam_point.view()).unwrap();
+    println!("{} -> {}", &adam_repr, &adam_point);
+    assert_eq!(conditional_bounds.parse(adam_repr), adam_point);
+
+    // Bounds can be written to and read from TOML, JSON and YAML, e.g. to version a search space alongside the results of an experiment.
+    let toml = conditional_bounds.to_toml().unwrap();
+    println!("{}", &toml);
+    assert_eq!(
+        Bounds::from_toml(&toml).unwrap().to_string(),
+        conditional_bounds.to_string()
+    );
+
+    // Besides the built-in transforms, user-defined transforms can be added by implementing BoundTransformTrait. Transforms must be strictly monotonic over the range of the bound, but may be decreasing.
+    let transformed_bounds = Bounds::new()
+        .add_continuous_with_transform("t1", 1e2, 1e-3, BoundTransform::Log10)
+        .add_continuous_with_transform("t2", 4.0, 0.0, BoundTransform::Sqrt)
+        .add_continuous_with_transform("t3", 10.0, 0.1, BoundTransform::Power(-0.5))
+        .add_continuous_with_transform("t4", 50.0, 1.0, BoundTransform::Reciprocal)
+        .add_continuous_with_transform("t5", 3.0, -3.0, BoundTransform::BiLog)
+        .add_continuous_with_transform(
+            "t6",
+            1.0,
+            -1.0,
+            BoundTransform::Affine {
+                scale: -2.0,
+                offset: 1.0,
+            },
+        )
+        .add_continuous_with_transform("t7", 2.0, -2.0, BoundTransform::custom("cube", Cube))
+        .add_discrete_with_transform("t8", 64, 1, BoundTransform::Reciprocal)
+        .build();
+
+    println!("{}", &transformed_bounds);
+
+    // Representations of points spread over each bound's range are recovered after parsing, i.e. inv_transform(transform(x)) == x
+    let ranges = [
+        (1e-3, 1e2),
+        (0.0, 4.0),
+        (0.1, 10.0),
+        (1.0, 50.0),
+        (-3.0, 3.0),
+        (-1.0, 1.0),
+        (-2.0, 2.0),
+        (1.0, 64.0),
+    ];
+    for i in 0..=100 {
+        let reprs: Vec<BoundRepr> = ranges
+            .iter()
+            .enumerate()
+            .map(|(d, (lower, upper))| {
+                let val = lower + (upper - lower) * i as f_ / 100.0;
+                match d {
+                    7 => BoundRepr::Discrete((format!("t{}", d + 1), val.round() as i_)),
+                    _ => BoundRepr::Continuous((format!("t{}", d + 1), val)),
+                }
+            })
+            .collect();
+        let x = transformed_bounds.parse(BoundReprs::new(reprs.clone()));
+        assert!(transformed_bounds.inside(x.view()));
+
+        let roundtrip = transformed_bounds.repr(x.view()).unwrap();
+        for (repr, val) in reprs.iter().zip(roundtrip.iter()) {
+            match (repr, val) {
+                (BoundRepr::Continuous((_, a)), BoundRepr::Continuous((_, b))) => {
+                    assert!((a - b).abs() <= 1e3 * f_::EPSILON * a.abs().max(1.0))
+                }
+                (BoundRepr::Discrete((_, a)), BoundRepr::Discrete((_, b))) => assert_eq!(a, b),
+                _ => panic!("Bound type of representation changed after parsing!"),
+            }
+        }
+    }
+
+    // Bounds with user-defined transforms cannot be written to a file
+    assert!(transformed_bounds.to_toml().is_err());
 
     // This constructor is also provided as a shortcut for building continuous Bounds with the same bounds for each dimension
     let auto_bounds = Bounds::new_continuous(3, 5.0, -5.0);
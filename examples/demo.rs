@@ -2,10 +2,10 @@ use labcat::{LABCAT, bounds::Bounds, f_};
 // use gplib::{ALGPA, GP, ALGPAtrait};
 use ndarray::{Array1, Array2};
 
-// Rosenbrock bjective function
-pub fn rosenbrock_vec(x: Vec<f64>) -> f64 {
-    let a = 1.0f64;
-    let b = 100.0f64;
+// Rosenbrock objective function, using the floating point precision selected by the `f64` feature
+pub fn rosenbrock_vec(x: Vec<f_>) -> f_ {
+    let a: f_ = 1.0;
+    let b: f_ = 100.0;
 
     let mut total = 0.0;
     for i in 0..(x.len() - 1) {
@@ -34,8 +34,14 @@ fn main() {
         // .restarts(true)
         // .beta(1.0 / bounds.dim())
         // .prior_sigma(0.1)
+        // .rescale_cap(Some(0.45))
+        // .theta_optimizer(ThetaOptimizer::Newton)
         // .init_pts_fn(|d| 2 * d + 1)
         // .forget_fn(|d| d * 7)
+        // .initial_design(InitialDesign::Lhs)
+        // .rotation(true)
+        // .pca_weighting(PCAWeighting::Linear)
+        // .output_warping(OutputWarping::None)
         .build();
 
     for i in 0..5 {
@@ -61,6 +67,12 @@ fn main() {
         // .target_val(0.1)
         // Maximum wall-clock time of execution
         // .max_time(std::time::Duration::new(1, 0))
+        // Extent of the trust region along every dimension
+        // .x_tol(1e-6)
+        // Relative improvement of the best output value within a number of samples
+        // .max_stagnation(50, 1e-3)
+        // Budget of the summed evaluation costs, if the objective function is set with set_target_fn_with_cost
+        // .max_cost(100.0)
 
         // Interval to print run summary to terminal if set, does not print to terminal if not specified
         .print_interval(25)
@@ -0,0 +1,44 @@
+use labcat::{LABCAT, bounds::Bounds, f_, recorder::Recorder};
+use ndarray::{Array1, Array2};
+
+// Rosenbrock objective function
+pub fn rosenbrock_vec(x: Vec<f_>) -> f_ {
+    let a: f_ = 1.0;
+    let b: f_ = 100.0;
+
+    let mut total = 0.0;
+    for i in 0..(x.len() - 1) {
+        total += b * (x[i + 1] - x[i].powi(2)).powi(2) + (a - x[i]).powi(2)
+    }
+    total
+}
+
+#[allow(non_snake_case)]
+fn true_fn(X: &Array2<f_>) -> Array1<f_> {
+    X.columns()
+        .into_iter()
+        .map(|col| rosenbrock_vec(col.to_vec()))
+        .collect()
+}
+
+fn main() -> std::io::Result<()> {
+    let bounds = Bounds::new_continuous(2, 5.0, -5.0);
+
+    let res = LABCAT::new(bounds)
+        // Record the observations, trust region, suggestion and GP mean/std on a 50 x 50 grid at every iteration
+        .record(Recorder::new().grid(50))
+        .build()
+        .set_target_fn(true_fn)
+        .max_samples(60)
+        .run();
+
+    println!("{}", res);
+
+    let recording = res.recording().expect("Recorder was set");
+    recording.write_csv("./target/labcat_run.csv")?;
+    recording.write_json("./target/labcat_run.json")?;
+    // One SVG per iteration, which can be combined into an animation using external tools (e.g. ImageMagick)
+    recording.write_svg_frames("./target/labcat_frames")?;
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use labcat::{LABCAT, f_, search_space::SearchSpace};
+use ndarray::Array1;
+
+// Search space of a model training run, with a field for every bound. Requires the `derive` feature.
+#[derive(Debug, Clone, SearchSpace)]
+struct TrainConfig {
+    #[labcat(range = 1e-5..1e-1, transform = "log")]
+    learning_rate: f_,
+    #[labcat(range = 0.0..0.5, step = 0.05)]
+    dropout: f_,
+    #[labcat(range = 1..=8)]
+    layers: i64,
+    #[labcat(levels = [16.0, 32.0, 64.0, 128.0])]
+    batch_size: f_,
+    #[labcat(categories = ["adam", "sgd", "rmsprop"])]
+    optimizer: String,
+    nesterov: bool,
+}
+
+// Toy objective written directly in terms of the config struct
+fn objective(cfg: TrainConfig) -> f_ {
+    let lr = (cfg.learning_rate.log10() + 3.0).powi(2);
+    let dropout = (cfg.dropout - 0.2).powi(2);
+    let layers = (cfg.layers as f_ - 4.0).powi(2) / 10.0;
+    let batch = (cfg.batch_size.log2() - 5.0).abs() / 10.0;
+    let optimizer = match cfg.optimizer.as_str() {
+        "adam" => 0.0,
+        "rmsprop" => 0.1,
+        _ => 0.3,
+    };
+    let nesterov = if cfg.nesterov { 0.0 } else { 0.05 };
+
+    lr + dropout + layers + batch + optimizer + nesterov
+}
+
+fn main() {
+    // The bounds are generated from the field attributes of the struct
+    let bounds = TrainConfig::bounds();
+    println!("{}", bounds);
+
+    let mut alg = LABCAT::new(bounds).build();
+
+    for _ in 0..40 {
+        let configs: Vec<TrainConfig> = alg.suggest_typed();
+        let y: Array1<f_> = configs.iter().map(|cfg| objective(cfg.clone())).collect();
+        alg.observe_typed(configs, y);
+    }
+
+    let best: TrainConfig = alg
+        .bounds()
+        .repr(alg.bounds().snap(alg.X_min().view()).view())
+        .expect("best point should be inside the bounds")
+        .into();
+    println!("Best: {:?}, y: {:.4}", best, alg.y_min());
+
+    // The objective can also be run automatically, with the bounds generated from the struct
+    let res = LABCAT::new_typed::<TrainConfig>()
+        .build()
+        .set_target_fn_typed(objective)
+        .max_samples(40)
+        .run();
+    println!("{}", res);
+}
@@ -0,0 +1,316 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Expr, ExprArray, ExprRange, Field, Fields, LitStr, RangeLimits, Type,
+    parse_macro_input, spanned::Spanned,
+};
+
+/// Derives `labcat::search_space::SearchSpace` for a struct with named fields, where every field
+/// is a bound of the search space.
+///
+/// Fields are configured with the `labcat` attribute:
+/// - `bool` fields are boolean bounds and need no attribute.
+/// - Float fields take `range = lower..upper`, with an optional `transform = "log"` (any name
+///   accepted by `BoundTransform::parse_transform`, e.g. `"power(0.5)"`) or `step = 0.05` for a
+///   quantized bound, or `levels = [0.1, 0.5, 2.0]` for an ordinal bound.
+/// - Integer fields take `range = lower..upper` or `range = lower..=upper`, with an optional
+///   `transform`.
+/// - `String` fields take `categories = ["a", "b"]`.
+#[proc_macro_derive(SearchSpace, attributes(labcat))]
+pub fn derive_search_space(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match search_space(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+enum Kind {
+    Float,
+    Int,
+    Bool,
+    Str,
+}
+
+#[derive(Default)]
+struct Attrs {
+    range: Option<ExprRange>,
+    transform: Option<LitStr>,
+    step: Option<Expr>,
+    levels: Option<ExprArray>,
+    categories: Option<ExprArray>,
+}
+
+/// Tokens of a field for the bounds, the conversion from `BoundReprs` and the conversion to `BoundReprs`.
+struct FieldTokens {
+    bound: TokenStream2,
+    from_reprs: TokenStream2,
+    to_repr: TokenStream2,
+}
+
+fn search_space(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "SearchSpace can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "SearchSpace can only be derived for structs",
+            ));
+        }
+    };
+
+    let tokens = fields.iter().map(field).collect::<syn::Result<Vec<_>>>()?;
+    let bounds = tokens.iter().map(|t| &t.bound);
+    let from_reprs = tokens.iter().map(|t| &t.from_reprs);
+    let to_reprs = tokens.iter().map(|t| &t.to_repr);
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::labcat::search_space::SearchSpace for #name #ty_generics #where_clause {
+            fn bounds() -> ::labcat::bounds::Bounds<::labcat::bounds::Ready> {
+                ::labcat::bounds::Bounds::new()
+                    #(#bounds)*
+                    .build()
+            }
+        }
+
+        impl #impl_generics ::std::convert::From<::labcat::bounds::BoundReprs> for #name #ty_generics #where_clause {
+            fn from(reprs: ::labcat::bounds::BoundReprs) -> Self {
+                fn missing(label: &str) -> ! {
+                    panic!("Bound \"{}\" could not be found in representation!", label)
+                }
+
+                #name {
+                    #(#from_reprs),*
+                }
+            }
+        }
+
+        impl #impl_generics ::std::convert::From<#name #ty_generics> for ::labcat::bounds::BoundReprs #where_clause {
+            fn from(x: #name #ty_generics) -> Self {
+                ::labcat::bounds::BoundReprs::new(vec![#(#to_reprs),*])
+            }
+        }
+    })
+}
+
+fn kind(ty: &Type) -> syn::Result<Kind> {
+    let ident = match ty {
+        Type::Path(path) => path.path.segments.last().map(|seg| seg.ident.to_string()),
+        _ => None,
+    };
+
+    match ident.as_deref() {
+        Some("f32" | "f64" | "f_") => Ok(Kind::Float),
+        Some(
+            "i_" | "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize",
+        ) => Ok(Kind::Int),
+        Some("bool") => Ok(Kind::Bool),
+        Some("String") => Ok(Kind::Str),
+        _ => Err(syn::Error::new(
+            ty.span(),
+            "SearchSpace fields must be floats, integers, bools or Strings",
+        )),
+    }
+}
+
+fn attrs(field: &Field) -> syn::Result<Attrs> {
+    let mut attrs = Attrs::default();
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("labcat")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("range") {
+                attrs.range = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("transform") {
+                let transform: LitStr = meta.value()?.parse()?;
+                check_transform(&transform)?;
+                attrs.transform = Some(transform);
+            } else if meta.path.is_ident("step") {
+                attrs.step = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("levels") {
+                attrs.levels = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("categories") {
+                attrs.categories = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error(
+                    "unsupported labcat attribute, expected range, transform, step, levels or categories",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(attrs)
+}
+
+/// Checks that `transform` is a name accepted by `BoundTransform::parse_transform`, so that an
+/// unknown transform fails at compile time instead of when the bounds are built.
+fn check_transform(transform: &LitStr) -> syn::Result<()> {
+    let value = transform.value();
+    let args = |name: &str| -> Option<Vec<f64>> {
+        value
+            .strip_prefix(name)?
+            .strip_prefix('(')?
+            .strip_suffix(')')?
+            .split(',')
+            .map(|arg| arg.trim().parse().ok().filter(|arg: &f64| arg.is_finite()))
+            .collect()
+    };
+
+    let known = match value.as_str() {
+        "linear" | "log" | "log10" | "bilog" | "logistic" | "logit" | "sqrt" | "reciprocal" => true,
+        _ => match (args("power").as_deref(), args("affine").as_deref()) {
+            (Some([p]), _) => *p != 0.0,
+            (_, Some([scale, _])) => *scale != 0.0,
+            _ => false,
+        },
+    };
+
+    match known {
+        true => Ok(()),
+        false => Err(syn::Error::new(
+            transform.span(),
+            format!(
+                "unknown transform \"{}\", expected linear, log, log10, bilog, logistic, sqrt, reciprocal, power(p) or affine(scale, offset)",
+                value
+            ),
+        )),
+    }
+}
+
+fn field(field: &Field) -> syn::Result<FieldTokens> {
+    let ident = field.ident.as_ref().expect("fields are named");
+    let label = ident.to_string();
+    let ty = &field.ty;
+    let attrs = attrs(field)?;
+
+    let transform = attrs.transform.as_ref().map(|t| {
+        quote! {
+            ::labcat::bounds::BoundTransform::parse_transform(#t)
+                .unwrap_or_else(|| panic!("Bound transform \"{}\" not recognized!", #t))
+        }
+    });
+    let range = |range: &Option<ExprRange>| match range {
+        Some(ExprRange {
+            start: Some(start),
+            end: Some(end),
+            limits,
+            ..
+        }) => Ok((start.clone(), end.clone(), *limits)),
+        _ => Err(syn::Error::new(
+            field.span(),
+            "expected a bounded range attribute, e.g. #[labcat(range = 0.0..1.0)]",
+        )),
+    };
+    let missing = quote! { .unwrap_or_else(|| missing(#label)) };
+
+    let tokens = match kind(ty)? {
+        Kind::Bool => FieldTokens {
+            bound: quote! { .add_boolean(#label) },
+            from_reprs: quote! { #ident: reprs.get_bool(#label) #missing },
+            to_repr: quote! { ::labcat::bounds::BoundRepr::Boolean((#label.into(), x.#ident)) },
+        },
+        Kind::Str => {
+            let categories = attrs.categories.as_ref().ok_or_else(|| {
+                syn::Error::new(
+                    field.span(),
+                    "String fields require a categories attribute, e.g. #[labcat(categories = [\"a\", \"b\"])]",
+                )
+            })?;
+            let categories = categories.elems.iter();
+
+            FieldTokens {
+                bound: quote! { .add_categorical(#label, vec![#(#categories),*]) },
+                from_reprs: quote! { #ident: reprs.get_str(#label) #missing .to_string() },
+                to_repr: quote! {
+                    ::labcat::bounds::BoundRepr::Categorical((#label.into(), x.#ident))
+                },
+            }
+        }
+        Kind::Float if attrs.levels.is_some() => {
+            let levels = attrs.levels.as_ref().expect("checked").elems.iter();
+
+            FieldTokens {
+                bound: quote! {
+                    .add_ordinal(#label, vec![#((#levels) as ::labcat::f_),*])
+                },
+                from_reprs: quote! { #ident: reprs.get_f64(#label) #missing as #ty },
+                to_repr: quote! {
+                    ::labcat::bounds::BoundRepr::Ordinal((#label.into(), x.#ident as ::labcat::f_))
+                },
+            }
+        }
+        Kind::Float => {
+            let (start, end, _) = range(&attrs.range)?;
+            let bound = match (&attrs.step, &transform) {
+                (Some(_), Some(_)) => {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        "step is only supported without a transform",
+                    ));
+                }
+                (Some(step), None) => quote! {
+                    .add_quantized_continuous(
+                        #label,
+                        (#end) as ::labcat::f_,
+                        (#start) as ::labcat::f_,
+                        (#step) as ::labcat::f_,
+                    )
+                },
+                (None, Some(transform)) => quote! {
+                    .add_continuous_with_transform(
+                        #label,
+                        (#end) as ::labcat::f_,
+                        (#start) as ::labcat::f_,
+                        #transform,
+                    )
+                },
+                (None, None) => quote! {
+                    .add_continuous(#label, (#end) as ::labcat::f_, (#start) as ::labcat::f_)
+                },
+            };
+
+            FieldTokens {
+                bound,
+                from_reprs: quote! { #ident: reprs.get_f64(#label) #missing as #ty },
+                to_repr: quote! {
+                    ::labcat::bounds::BoundRepr::Continuous((#label.into(), x.#ident as ::labcat::f_))
+                },
+            }
+        }
+        Kind::Int => {
+            let (start, end, limits) = range(&attrs.range)?;
+            // Half-open ranges exclude the end, as in Rust
+            let upper = match limits {
+                RangeLimits::HalfOpen(_) => quote! { ((#end) as ::labcat::i_ - 1) },
+                RangeLimits::Closed(_) => quote! { ((#end) as ::labcat::i_) },
+            };
+            let transform =
+                transform.unwrap_or(quote! { ::labcat::bounds::BoundTransform::Linear });
+
+            FieldTokens {
+                bound: quote! {
+                    .add_discrete_with_transform(#label, #upper, (#start) as ::labcat::i_, #transform)
+                },
+                from_reprs: quote! { #ident: reprs.get_i64(#label) #missing as #ty },
+                to_repr: quote! {
+                    ::labcat::bounds::BoundRepr::Discrete((#label.into(), x.#ident as ::labcat::i_))
+                },
+            }
+        }
+    };
+
+    Ok(tokens)
+}
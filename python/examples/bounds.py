@@ -8,6 +8,8 @@ b.add_discrete("d1", 10, -10)
 b.add_categorical("d2", ["1", "2", "3"])
 b.add_continuous("d3", 5, -5)
 b.add_continuous_with_transform("d4", 1000, 0.01, "log")
+b.add_ordinal("d5", [0.1, 0.5, 2.0, 10.0])
+b.add_quantized_continuous("d6", 1.0, 0.0, 0.05)
 b = b.build()
 
 # Bounds can also be parsed from a dict directly
@@ -16,6 +18,9 @@ bound_config = {
     "d2": {"type": "real", "space": "logit", "range": (0.01, 0.99)}, 
     "d3" : {"type": "cat", "categories": ["c1", "c2"]},  
     "d4" : {"type": "bool"},
+    "d5" : {"type": "ord", "levels": [0.1, 0.5, 2.0, 10.0]},
+    "d6" : {"type": "real", "range": (0.0, 1.0), "step": 0.05},
+    "d7" : {"type": "real", "range": (0.0, 0.99), "condition": ("d3", ["c1"])},
     }
 
 b = labcat.BoundsConfig()
@@ -36,6 +36,10 @@ def forget_fn(d):
 
 alg_config.forget_fn(forget_fn)
 
+# Initial design ("lhs", "random", "sobol" or "maximin") and trust region rotation
+alg_config.initial_design("lhs")
+alg_config.rotation(True)
+
 # After configuring the algorithm, the build() command enables the use of the ask-tell interface
 ask_tell = alg_config.build()
 
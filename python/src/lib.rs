@@ -1,7 +1,13 @@
+use labcat::acq::Acquisition;
+use labcat::hyp_opt::ThetaOptimizer;
 use labcat::bound_types::{BoundRepr, BoundTrait, BoundType};
 use labcat::bounds::{Bounds, Ready, BoundReprs};
+use labcat::bounds_array::InitialDesign;
 use labcat::bounds_transforms::BoundTransform;
-use labcat::{Auto, Config, Manual, LABCAT};
+use labcat::memory::{Archiving, ForgetStrategy, OutlierClipping, PCAWeighting};
+use labcat::output_warping::OutputWarping;
+use labcat::priors::Prior;
+use labcat::{Auto, Config, Manual, LABCAT, f_, i_};
 use numpy::{IntoPyArray, PyArray1, PyArray2};
 use std::fmt::Display;
 use pyo3::prelude::*;
@@ -16,6 +22,82 @@ use pyo3::types::{PyDict, PyList};
 //     matrix.to_owned().into_pyarray(py).into()
 // }
 
+/// Representation of the python value `item` for `bound`.
+fn repr_from_py(bound: &BoundType, item: &PyAny) -> BoundRepr {
+    match bound {
+        BoundType::Continuous(_) | BoundType::QuantizedContinuous(_) => {
+            let val = item.extract::<f_>()
+                .unwrap_or_else(|_| panic!("Python dict key for bound {} could not be parsed to float!", bound.label()));
+            BoundRepr::Continuous((bound.label().into(), val)) 
+        },
+        BoundType::Discrete(_) => {
+            let val = item.extract::<i_>().ok()
+                .unwrap_or_else(|| panic!("Python dict key for bound {} could not be parsed to int!", bound.label()));
+            BoundRepr::Discrete((bound.label().into(), val)) 
+        },
+        BoundType::Ordinal(_) => {
+            let val = item.extract::<f_>()
+                .unwrap_or_else(|_| panic!("Python dict key for bound {} could not be parsed to float!", bound.label()));
+            BoundRepr::Ordinal((bound.label().into(), val)) 
+        },
+        BoundType::Categorical(_) => {
+            let cat = item.extract::<String>().ok()
+                .unwrap_or_else(|| panic!("Python dict key for bound {} could not be parsed to String!", bound.label()));
+            BoundRepr::Categorical((bound.label().into(), cat)) 
+        },
+        BoundType::Boolean(_) => {
+            let bool = item.extract::<bool>()
+                .unwrap_or_else(|_| panic!("Python dict key for bound {} could not be parsed to bool!", bound.label()));
+            BoundRepr::Boolean((bound.label().into(), bool)) 
+        },
+    }
+}
+
+/// Python dict of the representation of a point, keyed by the labels of the bounds.
+fn reprs_to_dict<'py>(reprs: &BoundReprs, py: Python<'py>) -> Option<&'py PyDict> {
+    let map = PyDict::new(py);
+    for repr in reprs.iter() {
+        match repr {
+            BoundRepr::Continuous((str, val)) => map.set_item(str.clone(), *val).ok()?,
+            BoundRepr::Discrete((str, val)) => map.set_item(str, *val).ok()?,
+            BoundRepr::Ordinal((str, val)) => map.set_item(str, *val).ok()?,
+            BoundRepr::Categorical((str, cat)) => map.set_item(str, cat.clone()).ok()?,
+            BoundRepr::Boolean((str, b)) => map.set_item(str, *b).ok()?,
+        };
+    }
+
+    Some(map)
+}
+
+/// Representation of a point from a python dict keyed by the labels of the bounds.
+fn parse_prior(prior: &str, params: Vec<f_>) -> Prior {
+    Prior::parse_prior(prior, &params).unwrap_or_else(|| {
+        panic!(
+            "Prior \"{}\" with {} parameters not recognized!",
+            prior,
+            params.len()
+        )
+    })
+}
+
+fn dict_to_reprs(bounds: &Bounds<Ready>, x: &PyAny) -> BoundReprs {
+    let x_dict = x.extract::<&PyDict>().expect("Could not parse items in list to PyDict!");
+    let mut repr_vec = vec![];
+
+    for bound in bounds.iter_bounds() {
+        // Inactive conditional bounds can be left out of the python dict
+        let item = match x_dict.get_item(bound.label()) { //try to get boundrepr from python dict
+            Some(item) => item,
+            None => continue,
+        };
+        let repr = repr_from_py(bound, item);
+
+        repr_vec.push(repr);
+    }
+
+    BoundReprs::new(repr_vec)
+}
+
 #[pyclass(name = "BoundsConfig")]
 struct PyBoundsConfig {
     bounds: Bounds,
@@ -31,6 +113,7 @@ impl PyBoundsConfig {
     }
 
     fn parse_config(&mut self, dict: &PyDict) {
+        let mut conditions = vec![];
 
         for (k, v) in dict.iter() {
             let key = k
@@ -51,14 +134,34 @@ impl PyBoundsConfig {
             match bound_type.as_str() {
                 "int" => self.parse_discrete(&key, val),
                 "real" => self.parse_continuous(&key, val),
+                "ord" => self.parse_ordinal(&key, val),
                 "cat" => self.parse_categorical(&key, val),
                 "bool" => self.add_boolean(&key),
                 &_ => panic!("Bound type \"{}\" is not supported!", bound_type),
             }
+
+            if let Some(cond) = val.get_item("condition") {
+                let (parent, values) = cond.extract::<(String, &PyList)>().unwrap_or_else(|_| panic!("Condition for bound \"{}\" could not be parsed to (str, list)!",
+                    &key));
+                conditions.push((key, parent, values));
+            }
+        }
+
+        // Conditions are added last, as parents can be specified after their children
+        for (child, parent, values) in conditions {
+            self.add_condition(&child, &parent, values);
         }
 
     }
 
+    fn add_condition(&mut self, child: &str, parent: &str, values: &PyList) {
+        let bound = self.bounds.bound(parent).unwrap_or_else(|| panic!("Bound \"{}\" could not be found for condition!",
+            parent));
+        let values = values.iter().map(|item| repr_from_py(bound, item)).collect();
+
+        self.bounds = self.bounds.clone().add_condition(child, parent, values)
+    }
+
     fn add_categorical(&mut self, label: &str, categories: Vec<&str>) {
         self.bounds = self.bounds.clone().add_categorical(label, categories)
     }
@@ -67,25 +170,47 @@ impl PyBoundsConfig {
         self.bounds = self.bounds.clone().add_boolean(label);
     }
 
-    fn add_discrete(&mut self, label: &str, upper: i64, lower: i64) {
+    fn add_discrete(&mut self, label: &str, upper: i_, lower: i_) {
         self.bounds = self
             .bounds
             .clone()
             .add_discrete(label, upper, lower)
     }
 
-    fn add_continuous(&mut self, label: &str, upper: f64, lower: f64) {
+    fn add_continuous(&mut self, label: &str, upper: f_, lower: f_) {
         self.bounds = self
             .bounds
             .clone()
             .add_continuous(label, upper, lower)
     }
 
+    fn add_quantized_continuous(&mut self, label: &str, upper: f_, lower: f_, step: f_) {
+        self.bounds = self
+            .bounds
+            .clone()
+            .add_quantized_continuous(label, upper, lower, step)
+    }
+
+    fn add_ordinal(&mut self, label: &str, levels: Vec<f_>) {
+        self.bounds = self.bounds.clone().add_ordinal(label, levels)
+    }
+
+    fn add_context(&mut self, label: &str, upper: f_, lower: f_) {
+        self.bounds = self
+            .bounds
+            .clone()
+            .add_context(label, upper, lower)
+    }
+
+    fn add_fidelity(&mut self, label: &str, costs: Vec<f_>) {
+        self.bounds = self.bounds.clone().add_fidelity(label, costs)
+    }
+
     fn add_discrete_with_transform(
         &mut self,
         label: &str,
-        upper: i64,
-        lower: i64,
+        upper: i_,
+        lower: i_,
         transform: &str,
     ) {
         let trans = BoundTransform::parse_transform(transform).unwrap_or_else(|| panic!("Bound transform \"{}\" not recognized!",
@@ -102,8 +227,8 @@ impl PyBoundsConfig {
     fn add_continuous_with_transform(
         &mut self,
         label: &str,
-        upper: f64,
-        lower: f64,
+        upper: f_,
+        lower: f_,
         transform: &str,
     ) {
         let trans = BoundTransform::parse_transform(transform).unwrap_or_else(|| panic!("Bound transform \"{}\" not recognized!",
@@ -132,10 +257,17 @@ impl PyBoundsConfig {
         let range = dict.get_item("range").unwrap_or_else(|| panic!("Range specification for bound \"{}\" could not be found!",
             &label));
 
-        let range = range.extract::<(f64, f64)>().unwrap_or_else(|_| panic!("Range for bound \"{}\" could not be parsed to (f64, f64)!",
+        let range = range.extract::<(f_, f_)>().unwrap_or_else(|_| panic!("Range for bound \"{}\" could not be parsed to (float, float)!",
             &label));
 
-        self.add_continuous_with_transform(label, range.1, range.0, transform.to_string())
+        if let Some(step) = dict.get_item("step") {
+            let step = step.extract::<f_>().unwrap_or_else(|_| panic!("Step for bound \"{}\" could not be parsed to float!",
+                &label));
+
+            return self.add_quantized_continuous(label, range.1, range.0, step);
+        }
+
+        self.add_continuous_with_transform(label, range.1, range.0, &transform.to_string())
         
     }
 
@@ -154,10 +286,21 @@ impl PyBoundsConfig {
         let range = dict.get_item("range").unwrap_or_else(|| panic!("Range specification for bound \"{}\" could not be found!",
             &label));
 
-        let range = range.extract::<(i64, i64)>().unwrap_or_else(|_| panic!("Range for bound \"{}\" could not be parsed to (i64, i64)!",
+        let range = range.extract::<(i_, i_)>().unwrap_or_else(|_| panic!("Range for bound \"{}\" could not be parsed to (int, int)!",
+            &label));
+
+        self.add_discrete_with_transform(label, range.1, range.0, &transform.to_string())
+        
+    }
+
+    fn parse_ordinal(&mut self, label: &str, dict: &PyDict) {
+        let levels = dict.get_item("levels").unwrap_or_else(|| panic!("Levels for ordinal bound \"{}\" could not be found!",
+            &label));
+
+        let levels = levels.extract::<Vec<f_>>().unwrap_or_else(|_| panic!("Levels for ordinal bound \"{}\" could not be parsed to list of floats!",
             &label));
 
-        self.add_discrete_with_transform(label, range.1, range.0, transform.to_string())
+        self.add_ordinal(label, levels);
         
     }
 
@@ -196,7 +339,7 @@ struct PyBounds {
 impl PyBounds {
     
     #[new]
-    fn new(dim: usize, upper: f64, lower: f64) -> PyBounds {
+    fn new(dim: usize, upper: f_, lower: f_) -> PyBounds {
         PyBounds {
             bounds: Bounds::new_continuous(dim, upper, lower),
         }
@@ -214,19 +357,11 @@ impl PyBounds {
         self.bounds.bounds_arr().bounds_arr().clone().into_pyarray(py).into()
     }
 
-    fn repr<'py>(&self, x: &PyArray1<f64>, py: Python<'py>) -> Option<&'py PyList> {
+    fn repr<'py>(&self, x: &PyArray1<f_>, py: Python<'py>) -> Option<&'py PyList> {
         let x = unsafe { x.as_array() };
 
         let reprs = self.bounds.repr(x)?;
-        let map = PyDict::new(py);
-        for repr in reprs.iter() {
-            match repr {
-                BoundRepr::Continuous((str, val)) => map.set_item(str.clone(), *val).ok()?,
-                BoundRepr::Discrete((str, val)) => map.set_item(str, *val).ok()?,
-                BoundRepr::Categorical((str, cat)) => map.set_item(str, cat.clone()).ok()?,
-                BoundRepr::Boolean((str, b)) => map.set_item(str, *b).ok()?,
-            };
-        }
+        let map = reprs_to_dict(&reprs, py)?;
 
         let list = PyList::empty(py);
         list.append(map).ok()?;
@@ -239,41 +374,7 @@ impl PyBounds {
         let py_list = PyList::empty(py);
 
         for x in x.iter() {
-            
-            let x_dict = x.extract::<&PyDict>().expect("Could not parse items in list to PyDict!");
-            let mut repr_vec = vec![];
-
-            for bound in self.bounds.iter_bounds() {
-                let item = x_dict.get_item(bound.label()) //try to get boundrepr from python dict
-                    .unwrap_or_else(|| panic!("Bound {} could not be found in python dict!", bound.label()));
-                    
-                let repr = match bound {
-                    BoundType::Continuous(_) => {
-                        let val = item.extract::<f64>()
-                            .unwrap_or_else(|_| panic!("Python dict key for bound {} could not be parsed to f64!", bound.label()));
-                        BoundRepr::Continuous((bound.label().into(), val)) 
-                    },
-                    BoundType::Discrete(_) => {
-                        let val = item.extract::<i64>().ok()
-                            .unwrap_or_else(|| panic!("Python dict key for bound {} could not be parsed to i64!", bound.label()));
-                        BoundRepr::Discrete((bound.label().into(), val)) 
-                    },
-                    BoundType::Categorical(_) => {
-                        let cat = item.extract::<String>().ok()
-                            .unwrap_or_else(|| panic!("Python dict key for bound {} could not be parsed to String!", bound.label()));
-                        BoundRepr::Categorical((bound.label().into(), cat)) 
-                    },
-                    BoundType::Boolean(_) => {
-                        let bool = item.extract::<bool>()
-                            .unwrap_or_else(|_| panic!("Python dict key for bound {} could not be parsed to bool!", bound.label()));
-                        BoundRepr::Boolean((bound.label().into(), bool)) 
-                    },
-                };
-
-                repr_vec.push(repr);
-            }
-
-            let reprs = BoundReprs::new(repr_vec);
+            let reprs = dict_to_reprs(&self.bounds, x);
 
             py_list.append(self.bounds.parse(reprs).into_pyarray(py)).ok()?;
         }
@@ -297,18 +398,137 @@ impl PyLABCATConfig {
         }
     }
 
-    fn beta(&mut self, beta: f64) {
+    fn beta(&mut self, beta: f_) {
         self.labcat.beta(beta);
     }
 
-    fn prior_sigma(&mut self, prior_sigma: f64) {
+    fn prior_sigma(&mut self, prior_sigma: f_) {
         self.labcat.prior_sigma(prior_sigma);
     }
 
+    fn length_scale_prior(&mut self, prior: &str, params: Vec<f_>) {
+        self.labcat.length_scale_prior(parse_prior(prior, params));
+    }
+
+    fn length_scale_prior_of(&mut self, label: &str, prior: &str, params: Vec<f_>) {
+        self.labcat
+            .length_scale_prior_of(label, parse_prior(prior, params));
+    }
+
+    fn rescale_cap(&mut self, cap: Option<f_>) {
+        self.labcat.rescale_cap(cap);
+    }
+
+    fn theta_optimizer(
+        &mut self,
+        optimizer: &str,
+        restarts: Option<usize>,
+        max_iter: Option<usize>,
+        tol: Option<f_>,
+    ) {
+        let optimizer = match optimizer {
+            "newton" => ThetaOptimizer::Newton,
+            "lbfgs" => ThetaOptimizer::Lbfgs {
+                restarts: restarts.unwrap_or(5),
+                max_iter: max_iter.unwrap_or(50),
+                tol: tol.unwrap_or(1e-6),
+            },
+            _ => panic!("Theta optimizer \"{}\" not recognized!", optimizer),
+        };
+
+        self.labcat.theta_optimizer(optimizer);
+    }
+
+    fn theta_sampling(&mut self, n_samples: usize, burn_in: usize, thin: usize) {
+        self.labcat.theta_sampling(n_samples, burn_in, thin);
+    }
+
     pub fn init_fn(&mut self, f: PyObject) {
         self.labcat.init_pts_fn(f);
     }
 
+    fn initial_design(&mut self, design: &str) {
+        let design = InitialDesign::parse_design(design).unwrap_or_else(|| panic!("Initial design \"{}\" not recognized!",
+            design));
+
+        self.labcat.initial_design(design);
+    }
+
+    fn rotation(&mut self, rotation: bool) {
+        self.labcat.rotation(rotation);
+    }
+
+    fn pca_weighting(&mut self, weighting: &str, param: Option<f_>) {
+        let weighting = match (weighting, param) {
+            ("linear", _) => PCAWeighting::Linear,
+            ("rank", _) => PCAWeighting::Rank,
+            ("softmin", Some(temp)) => PCAWeighting::Softmin(temp),
+            ("topk", Some(k)) => PCAWeighting::TopK(k as usize),
+            ("softmin", None) | ("topk", None) => panic!("PCA weighting \"{}\" requires a parameter!",
+                weighting),
+            (&_, _) => panic!("PCA weighting \"{}\" not recognized!", weighting),
+        };
+
+        self.labcat.pca_weighting(weighting);
+    }
+
+    fn rotation_smoothing(&mut self, alpha: f_) {
+        self.labcat.rotation_smoothing(alpha);
+    }
+
+    fn acquisition(&mut self, acquisition: &str) {
+        let acquisition = Acquisition::parse_acquisition(acquisition)
+            .unwrap_or_else(|| panic!("Acquisition function \"{}\" not recognized!", acquisition));
+
+        self.labcat.acquisition(acquisition);
+    }
+
+    fn output_warping(&mut self, warping: &str, param: Option<f_>) {
+        let warping = OutputWarping::parse_output_warping(warping, param)
+            .unwrap_or_else(|| panic!("Output warping \"{}\" not recognized!", warping));
+
+        self.labcat.output_warping(warping);
+    }
+
+    fn outlier_clipping(&mut self, clipping: &str, param: Option<f_>) {
+        let clipping = match (clipping, param) {
+            ("none", _) => OutlierClipping::None,
+            ("mad", Some(k)) => OutlierClipping::Mad(k),
+            ("quantile", Some(q)) => OutlierClipping::Quantile(q),
+            ("mad", None) | ("quantile", None) => panic!("Outlier clipping \"{}\" requires a parameter!",
+                clipping),
+            (&_, _) => panic!("Outlier clipping \"{}\" not recognized!", clipping),
+        };
+
+        self.labcat.outlier_clipping(clipping);
+    }
+
+    fn forget_strategy(&mut self, strategy: &str) {
+        let strategy = ForgetStrategy::parse_forget_strategy(strategy)
+            .unwrap_or_else(|| panic!("Forget strategy \"{}\" not recognized!", strategy));
+
+        self.labcat.forget_strategy(strategy);
+    }
+
+    fn archive_forgotten(&mut self, archiving: &str) {
+        let archiving = Archiving::parse_archiving(archiving)
+            .unwrap_or_else(|| panic!("Archiving \"{}\" not recognized!", archiving));
+
+        self.labcat.archive_forgotten(archiving);
+    }
+
+    fn restarts(&mut self, restarts: bool) {
+        self.labcat.restarts(restarts);
+    }
+
+    fn min_trust_region(&mut self, len: f_) {
+        self.labcat.min_trust_region(len);
+    }
+
+    fn max_trust_region(&mut self, len: f_) {
+        self.labcat.max_trust_region(len);
+    }
+
     pub fn forget_fn(&mut self, f: PyObject) {
         self.labcat.forget_fn(f);
     }
@@ -342,24 +562,102 @@ impl PyLABCATManual {
         (x.into_pyarray(py).into(), y.into_pyarray(py).into())
     }
 
+    pub fn archived_observations(&mut self, py: Python<'_>) -> Option<(PyObject, PyObject)> {
+        let (x, y) = self.labcat.archived_observations()?;
+
+        Some((x.into_pyarray(py).into(), y.into_pyarray(py).into()))
+    }
+
     pub fn thetas(&mut self, py: Python<'_>) -> PyObject {
         let thetas = self.labcat.thetas().to_owned();
 
         thetas.into_pyarray(py).into()
     }
 
+    pub fn log_marginal_likelihood(&mut self) -> Option<f_> {
+        self.labcat.log_marginal_likelihood()
+    }
+
     pub fn suggest(&mut self, py: Python<'_>) -> PyObject {
         self.labcat.suggest(py).into_pyarray(py).into()
     }
 
-    pub fn observe(&mut self, x: &PyArray2<f64>, y: &PyArray1<f64>) {
+    pub fn suggest_labelled<'py>(&mut self, py: Python<'py>) -> Option<&'py PyList> {
+        let list = PyList::empty(py);
+        for reprs in self.labcat.suggest_labelled(py).iter() {
+            list.append(reprs_to_dict(reprs, py)?).ok()?;
+        }
+
+        Some(list)
+    }
+
+    pub fn suggest_with_context(&mut self, context: &PyArray1<f_>, py: Python<'_>) -> PyObject {
+        let context = unsafe { context.as_array().to_owned() };
+
+        self.labcat.suggest_with_context(context, py).into_pyarray(py).into()
+    }
+
+    pub fn suggest_with_fidelity(&mut self, py: Python<'_>) -> (PyObject, usize) {
+        let (suggestion, fidelity) = self.labcat.suggest_with_fidelity(py);
+
+        (suggestion.into_pyarray(py).into(), fidelity)
+    }
+
+    pub fn fix_parameter(&mut self, label: &str, value: f_) {
+        self.labcat.fix_parameter(label, value)
+    }
+
+    pub fn unfix_parameter(&mut self, label: &str) {
+        self.labcat.unfix_parameter(label)
+    }
+
+    pub fn fixed_parameters(&self) -> Vec<(String, f_)> {
+        self.labcat.fixed_parameters()
+    }
+
+    pub fn observe_with_fidelity(&mut self, x: &PyArray2<f_>, y: &PyArray1<f_>, fidelity: usize) {
         unsafe {
-            self.labcat
-                .observe(x.as_array().to_owned(), y.as_array().to_owned())
+            self.labcat.observe_with_fidelity(
+                x.as_array().to_owned(),
+                y.as_array().to_owned(),
+                fidelity,
+            )
         }
     }
 
-    pub fn predict(&mut self, x: &PyArray2<f64>, py: Python<'_>) -> PyObject {
+    pub fn observe_labelled(&mut self, x: &PyList, y: &PyArray1<f_>) {
+        let y = unsafe { y.as_array().to_owned() };
+        let x = x.iter().map(|x| dict_to_reprs(self.labcat.bounds(), x)).collect();
+
+        self.labcat.observe_labelled(x, y);
+    }
+
+    pub fn observe(&mut self, x: &PyArray2<f_>, y: &PyArray1<f_>, cost: Option<&PyArray1<f_>>) {
+        unsafe {
+            match cost {
+                Some(c) => self.labcat.observe_with_cost(
+                    x.as_array().to_owned(),
+                    y.as_array().to_owned(),
+                    c.as_array().to_owned(),
+                ),
+                None => self
+                    .labcat
+                    .observe(x.as_array().to_owned(), y.as_array().to_owned()),
+            }
+        }
+    }
+
+    pub fn trust_region(&mut self, py: Python<'_>) -> Option<(PyObject, PyObject, PyObject)> {
+        let tr = self.labcat.trust_region()?;
+
+        Some((
+            tr.centre().to_owned().into_pyarray(py).into(),
+            tr.axes().to_owned().into_pyarray(py).into(),
+            tr.side_lengths().into_pyarray(py).into(),
+        ))
+    }
+
+    pub fn predict(&mut self, x: &PyArray2<f_>, py: Python<'_>) -> PyObject {
         unsafe{
             self.labcat.predict(x.as_array().to_owned()).0.into_pyarray(py).into()
         }
@@ -385,11 +683,11 @@ struct PyLABCATAuto {
 
 #[pymethods]
 impl PyLABCATAuto {
-    fn target_tol(&mut self, tol: f64) {
+    fn target_tol(&mut self, tol: f_) {
         self.labcat.target_tol(tol);
     }
 
-    fn target_val(&mut self, val: f64) {
+    fn target_val(&mut self, val: f_) {
         self.labcat.target_val(val);
     }
 
@@ -405,6 +703,18 @@ impl PyLABCATAuto {
         self.labcat.print_interval(interval);
     }
 
+    fn x_tol(&mut self, tol: f_) {
+        self.labcat.x_tol(tol);
+    }
+
+    fn max_stagnation(&mut self, n: usize, rel_improvement: f_) {
+        self.labcat.max_stagnation(n, rel_improvement);
+    }
+
+    fn max_cost(&mut self, budget: f_) {
+        self.labcat.max_cost(budget);
+    }
+
     pub fn run(&mut self, py: Python<'_>) -> OptimizationResult {
         let res = self.labcat.run(py);
 
@@ -422,7 +732,7 @@ struct OptimizationResult {
     term_reason: String,
     n_samples: usize,
     min_x: PyObject,
-    min_y: f64,
+    min_y: f_,
 }
 
 #[pymethods]
@@ -17,33 +17,105 @@ use crate::{
     utils::{Array2Utils, ArrayView1Utils},
 };
 
+/// Acquisition function maximised to select the next sample.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Acquisition {
+    /// Expected improvement.
+    #[default]
+    EI,
+    /// Expected improvement per unit of predicted evaluation cost.
+    EIPerCost,
+}
+
+impl Acquisition {
+    pub fn parse_acquisition(acq: &str) -> Option<Acquisition> {
+        match acq.to_lowercase().as_str() {
+            "ei" => Some(Acquisition::EI),
+            "ei_per_cost" => Some(Acquisition::EIPerCost),
+            _ => None,
+        }
+    }
+}
+
+/// Number of the best candidates refined by the compass search, its initial step relative to the
+/// half side length of the local search domain, the relative step at which it stops and the
+/// maximum number of its iterations.
+const REFINED_CANDIDATES: usize = 3;
+const COMPASS_STEP: f_ = 0.1;
+const COMPASS_MIN_STEP: f_ = 1e-3;
+const COMPASS_MAX_ITER: usize = 50;
+
 pub trait ExpectedImprovement {
     fn ei(&self, x: ArrayView1<f_>) -> f_;
-    fn optimize_ei(&self, n: usize) -> Result<(f_, Array1<f_>)>;
-    fn random_valid_pt(&self, n: usize) -> Result<(f_, Array1<f_>)>;
+    fn ei_with_incumbent(&self, x: ArrayView1<f_>, y_best: f_) -> f_;
+    fn candidates(&self, n: usize) -> Array2<f_>;
+    fn optimize_acq_over<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        X: Array2<f_>,
+        acq: F,
+    ) -> Result<(f_, Array1<f_>)>;
+    fn compass_search<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        best: (f_, Array1<f_>),
+        acq: F,
+    ) -> (f_, Array1<f_>);
+    fn local_search<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        best: (f_, Array1<f_>),
+        acq: F,
+    ) -> (f_, Array1<f_>);
+    fn random_valid_pt<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        n: usize,
+        acq: F,
+    ) -> Result<(f_, Array1<f_>)>;
 }
 
 impl<kern: Kernel> ExpectedImprovement for GP<kern> {
     //checked
     fn ei(&self, x: ArrayView1<f_>) -> f_ {
+        self.ei_with_incumbent(x, self.mem.y_prime_min())
+    }
+
+    /// Expected improvement over `y_best`, in the rescaled output space.
+    #[allow(clippy::unnecessary_cast)] // statrs only supports f64
+    fn ei_with_incumbent(&self, x: ArrayView1<f_>, y_best: f_) -> f_ {
         let (mean, sigma) = self.predict_single(x).unwrap();
 
-        let z = (self.mem.y_prime_min() - mean) / sigma;
+        let z = (y_best - mean) / sigma;
 
-        sigma * (z * self.n.cdf(z) as f_ + self.n.pdf(z) as f_)
+        sigma * (z * self.n.cdf(z as f64) as f_ + self.n.pdf(z as f64) as f_)
     }
 
-    fn optimize_ei(&self, n: usize) -> Result<(f_, Array1<f_>)> {
-        let X = Array2::random((self.dim, n), Uniform::new(-self.beta, self.beta));
+    /// Uniform random candidates in the local search domain, with uniform random categories
+    /// along the categorical dimensions.
+    fn candidates(&self, n: usize) -> Array2<f_> {
+        let mut X = Array2::random((self.dim, n), Uniform::new(-self.beta, self.beta));
+
+        for d in self.categorical.iter() {
+            let n_cats = self.bounds.bounds_arr()[(*d, 1)] as usize;
+            X.row_mut(*d)
+                .map_inplace(|x| *x = rand::rng().random_range(0..n_cats) as f_ + 0.5);
+        }
 
+        X
+    }
+
+    /// Maximiser of `acq` over the candidates `X`, refining the best candidates with a compass
+    /// search over the continuous dimensions and a local search over the categorical dimensions.
+    fn optimize_acq_over<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        X: Array2<f_>,
+        acq: F,
+    ) -> Result<(f_, Array1<f_>)> {
         let mut res = vec![]; //working
         res.extend(
             X.columns()
                 .into_iter()
-                .map(|col| (self.ei(col), col.to_owned())),
+                .map(|col| (acq(col), col.to_owned())),
         );
 
-        let max = res
+        let mut valid: Vec<(f_, Array1<f_>)> = res
             .into_iter()
             .filter_map(|tup| match tup.0.is_finite() {
                 true => Some(tup),
@@ -60,17 +132,106 @@ impl<kern: Kernel> ExpectedImprovement for GP<kern> {
                     false => None,
                 },
             )
-            .max_by(|(a, _), (b, _)| (a).total_cmp(b));
+            .collect();
+        valid.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let max = valid
+            .into_iter()
+            .take(REFINED_CANDIDATES)
+            .map(|start| self.local_search(self.compass_search(start, &acq), &acq))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
 
         match max {
             Some(max) => Ok(max),
-            None => self.random_valid_pt(self.dim * 100),
+            None => self.random_valid_pt(self.dim * 100, &acq),
         }
     }
 
-    fn random_valid_pt(&self, n: usize) -> Result<(f_, Array1<f_>)> {
+    /// Compass search over the continuous dimensions of `best`, moving to the best step along a
+    /// single dimension and halving the step once none improves, Kolda et al. (2003). Steps stay
+    /// inside the bounds and, if `best` is inside it, the local search domain.
+    fn compass_search<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        mut best: (f_, Array1<f_>),
+        acq: F,
+    ) -> (f_, Array1<f_>) {
+        let continuous: Vec<usize> = (0..self.dim)
+            .filter(|d| !self.categorical.contains(d))
+            .collect();
+        let in_dom = |x: &Array1<f_>| continuous.iter().all(|d| x[*d].abs() <= self.beta);
+        let local = in_dom(&best.1);
+
+        let mut step = COMPASS_STEP * self.beta;
+        for _ in 0..COMPASS_MAX_ITER {
+            if step < COMPASS_MIN_STEP * self.beta || continuous.is_empty() {
+                break;
+            }
+
+            let neighbour = continuous
+                .iter()
+                .flat_map(|d| [(*d, step), (*d, -step)])
+                .map(|(d, step)| {
+                    let mut x = best.1.clone();
+                    x[d] += step;
+                    x
+                })
+                .filter(|x| !local || in_dom(x))
+                .filter(|x| self.bounds.inside(self.mem.x_test(x.view()).view()))
+                .map(|x| (acq(x.view()), x))
+                .filter(|(val, x)| val.is_finite() && !self.mem.in_memory(x.view()))
+                .max_by(|(a, _), (b, _)| a.total_cmp(b));
+
+            match neighbour {
+                Some(neighbour) if neighbour.0 > best.0 => best = neighbour,
+                _ => step /= 2.0,
+            }
+        }
+
+        best
+    }
+
+    /// Greedy search over the categories of the categorical dimensions of `best`, moving to the
+    /// best neighbour that differs along a single categorical dimension until none improves.
+    fn local_search<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        mut best: (f_, Array1<f_>),
+        acq: F,
+    ) -> (f_, Array1<f_>) {
+        for _ in 0..self.categorical.len() * 10 {
+            let neighbour = self
+                .categorical
+                .iter()
+                .flat_map(|d| {
+                    let n_cats = self.bounds.bounds_arr()[(*d, 1)] as usize;
+                    (0..n_cats).map(move |cat| (*d, cat as f_ + 0.5))
+                })
+                .filter(|(d, code)| best.1[*d].floor() != code.floor())
+                .map(|(d, code)| {
+                    let mut x = best.1.clone();
+                    x[d] = code;
+                    (acq(x.view()), x)
+                })
+                .filter(|(val, x)| val.is_finite() && !self.mem.in_memory(x.view()))
+                .max_by(|(a, _), (b, _)| a.total_cmp(b));
+
+            match neighbour {
+                Some(neighbour) if neighbour.0 > best.0 => best = neighbour,
+                _ => break,
+            }
+        }
+
+        best
+    }
+
+    /// Random candidate that has not been evaluated and lies inside the bounds, with its value of
+    /// `acq`.
+    fn random_valid_pt<F: Fn(ArrayView1<f_>) -> f_>(
+        &self,
+        n: usize,
+        acq: F,
+    ) -> Result<(f_, Array1<f_>)> {
         for _ in 0..n {
-            let x = Array1::random((self.dim,), Uniform::new(-self.beta, self.beta));
+            let x = self.candidates(1).column(0).to_owned();
 
             if self.mem.in_memory(x.view()) {
                 continue;
@@ -80,7 +241,7 @@ impl<kern: Kernel> ExpectedImprovement for GP<kern> {
                 continue;
             }
 
-            return Ok((self.ei(x.view()), x));
+            return Ok((acq(x.view()), x));
         }
 
         bail!("EI point not found!")
@@ -2,7 +2,6 @@ use std::fmt::Display;
 
 use enum_dispatch::enum_dispatch;
 use ndarray::{Array1, AssignElem, arr1};
-use rand::{Rng, rng};
 
 use crate::{
     bounds_transforms::{BoundTransform, BoundTransformTrait, BoundTransformType},
@@ -13,10 +12,17 @@ use crate::{
 pub trait BoundTrait {
     fn label(&self) -> &str;
     fn inside(&self, x: &f_) -> bool;
+    /// Closest value that the bound can take.
+    fn snap(&self, x: &f_) -> f_;
     fn repr(&self, x: &f_) -> Option<BoundRepr>;
     fn parse(&self, x: &BoundRepr) -> f_;
     fn enum_var(&self) -> BoundType;
     fn bound_arr(&self) -> Array1<f_>;
+    /// Value in the original units of the bound, without snapping. Categorical and boolean bounds
+    /// have no numeric units and keep their codes.
+    fn untransform(&self, x: &f_) -> f_ {
+        *x
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +30,7 @@ pub struct Continuous {
     label: String,
     upper: f_,
     lower: f_,
+    range: (f_, f_), // untransformed lower and upper values
     transform: BoundTransformType,
 }
 
@@ -33,6 +40,7 @@ impl Continuous {
             label: label.into(),
             upper,
             lower,
+            range: (lower, upper),
             transform: BoundTransform::new_transform(BoundTransform::Linear),
         }
     }
@@ -44,16 +52,27 @@ impl Continuous {
         transform: BoundTransform,
     ) -> Self {
         let transform = BoundTransform::new_transform(transform);
-        let upper = transform.transform(upper);
-        let lower = transform.transform(lower);
+        let (t_lower, t_upper) = transform
+            .transform_range((lower, upper))
+            .unwrap_or_else(|msg| panic!("Invalid transform for bound \"{}\": {}!", label, msg));
 
         Continuous {
             label: label.into(),
-            upper,
-            lower,
+            upper: t_upper,
+            lower: t_lower,
+            range: (lower, upper),
             transform,
         }
     }
+
+    /// Untransformed lower and upper values of the bound.
+    pub fn range(&self) -> (f_, f_) {
+        self.range
+    }
+
+    pub fn transform(&self) -> &BoundTransformType {
+        &self.transform
+    }
 }
 
 impl BoundTrait for Continuous {
@@ -65,6 +84,10 @@ impl BoundTrait for Continuous {
         x <= &self.upper && x >= &self.lower
     }
 
+    fn snap(&self, x: &f_) -> f_ {
+        x.clamp(self.lower, self.upper)
+    }
+
     fn repr(&self, x: &f_) -> Option<BoundRepr> {
         if self.inside(x) {
             Some(BoundRepr::Continuous((
@@ -112,6 +135,10 @@ impl BoundTrait for Continuous {
     fn bound_arr(&self) -> Array1<f_> {
         arr1(&[self.lower, self.upper])
     }
+
+    fn untransform(&self, x: &f_) -> f_ {
+        self.transform.inv_transform(*x)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -119,6 +146,7 @@ pub struct Discrete {
     label: String,
     upper: i_,
     lower: i_,
+    transformed: (f_, f_), // transformed lower and upper values
     transform: BoundTransformType,
 }
 
@@ -128,6 +156,7 @@ impl Discrete {
             label: label.into(),
             upper,
             lower,
+            transformed: (lower as f_, upper as f_),
             transform: BoundTransform::new_transform(BoundTransform::Linear),
         }
     }
@@ -142,13 +171,28 @@ impl Discrete {
             panic!("Logistic transformation not supported for discrete bound!")
         };
 
+        let transform = BoundTransform::new_transform(transform);
+        let transformed = transform
+            .transform_range((lower as f_, upper as f_))
+            .unwrap_or_else(|msg| panic!("Invalid transform for bound \"{}\": {}!", label, msg));
+
         Discrete {
             label: label.into(),
             upper,
             lower,
-            transform: BoundTransform::new_transform(transform),
+            transformed,
+            transform,
         }
     }
+
+    /// Lower and upper values of the bound.
+    pub fn range(&self) -> (i_, i_) {
+        (self.lower, self.upper)
+    }
+
+    pub fn transform(&self) -> &BoundTransformType {
+        &self.transform
+    }
 }
 
 impl BoundTrait for Discrete {
@@ -157,15 +201,24 @@ impl BoundTrait for Discrete {
     }
 
     fn inside(&self, x: &f_) -> bool {
-        x <= &self.transform.transform(self.upper as f_)
-            && x >= &self.transform.transform(self.lower as f_)
+        x <= &self.transformed.1 && x >= &self.transformed.0
+    }
+
+    /// Transformed value of the closest integer.
+    fn snap(&self, x: &f_) -> f_ {
+        let val = self
+            .transform
+            .inv_transform(*x)
+            .round()
+            .clamp(self.lower as f_, self.upper as f_);
+        self.transform.transform(val)
     }
 
     fn repr(&self, x: &f_) -> Option<BoundRepr> {
         if self.inside(x) {
             Some(BoundRepr::Discrete((
                 self.label.clone(),
-                self.transform.inv_transform(*x).floor() as i_,
+                self.transform.inv_transform(*x).round() as i_,
             )))
         } else {
             None
@@ -206,10 +259,217 @@ impl BoundTrait for Discrete {
     }
 
     fn bound_arr(&self) -> Array1<f_> {
-        arr1(&[
-            self.transform.transform(self.lower as f_),
-            self.transform.transform(self.upper as f_),
-        ])
+        arr1(&[self.transformed.0, self.transformed.1])
+    }
+
+    fn untransform(&self, x: &f_) -> f_ {
+        self.transform.inv_transform(*x)
+    }
+}
+
+/// Continuous bound that only takes values at integer multiples of `step` from the lower bound.
+#[derive(Debug, Clone)]
+pub struct QuantizedContinuous {
+    label: String,
+    upper: f_,
+    lower: f_,
+    step: f_,
+}
+
+impl QuantizedContinuous {
+    pub fn new(label: &str, upper: f_, lower: f_, step: f_) -> Self {
+        if step <= 0.0 {
+            panic!("Step size of quantized continuous bound must be positive!")
+        }
+
+        // Upper bound is lowered to the last step inside the range
+        let steps = (upper - lower) / step;
+        let n_steps = (steps + 4.0 * f_::EPSILON * steps.max(1.0)).floor();
+        if n_steps < 1.0 {
+            panic!("Step size of quantized continuous bound cannot be larger than its range!")
+        }
+
+        QuantizedContinuous {
+            label: label.into(),
+            upper: lower + n_steps * step,
+            lower,
+            step,
+        }
+    }
+
+    /// Lower value and last step of the bound.
+    pub fn range(&self) -> (f_, f_) {
+        (self.lower, self.upper)
+    }
+
+    pub fn step(&self) -> f_ {
+        self.step
+    }
+
+    /// Tolerance of a value lying on a step, relative to the precision of [`f_`].
+    pub fn tol(&self) -> f_ {
+        let scale = self.lower.abs().max(self.upper.abs());
+        4.0 * f_::EPSILON * self.step.max(self.upper - self.lower).max(scale)
+    }
+}
+
+impl BoundTrait for QuantizedContinuous {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn inside(&self, x: &f_) -> bool {
+        x <= &self.upper && x >= &self.lower
+    }
+
+    /// Closest step inside the bound.
+    fn snap(&self, x: &f_) -> f_ {
+        let n_steps = ((self.upper - self.lower) / self.step).round();
+        self.lower + ((x - self.lower) / self.step).round().clamp(0.0, n_steps) * self.step
+    }
+
+    fn repr(&self, x: &f_) -> Option<BoundRepr> {
+        if self.inside(x) {
+            Some(BoundRepr::Continuous((self.label.clone(), self.snap(x))))
+        } else {
+            None
+        }
+    }
+
+    fn parse(&self, x: &BoundRepr) -> f_ {
+        match x {
+            BoundRepr::Continuous((label, val)) => {
+                if label != self.label() {
+                    panic!(
+                        "Label of bound representaion {} does not match label of bound {}!",
+                        x.label(),
+                        label
+                    )
+                };
+
+                if !self.inside(val) || (self.snap(val) - val).abs() > self.tol() {
+                    panic!(
+                        "Parsed value for bound representation {} does not lie on a step of bound {}!",
+                        x.label(),
+                        label
+                    )
+                };
+
+                self.snap(val)
+            }
+            _ => panic!(
+                "Bound representation {} cannot be parsed to quantized continuous bound type!",
+                x
+            ),
+        }
+    }
+
+    fn enum_var(&self) -> BoundType {
+        BoundType::QuantizedContinuous(self.clone())
+    }
+
+    fn bound_arr(&self) -> Array1<f_> {
+        arr1(&[self.lower, self.upper])
+    }
+}
+
+/// Bound over an ordered list of numeric levels, encoded by the index of the level.
+#[derive(Debug, Clone)]
+pub struct Ordinal {
+    label: String,
+    levels: Vec<f_>,
+}
+
+impl Ordinal {
+    pub fn new(label: &str, levels: Vec<f_>) -> Self {
+        if levels.len() < 2 {
+            panic!("Ordinal bound must have at least two levels!");
+        }
+
+        if levels.windows(2).any(|pair| pair[0] >= pair[1]) {
+            panic!("Levels of ordinal bound must be strictly increasing!");
+        }
+
+        Ordinal {
+            label: label.into(),
+            levels,
+        }
+    }
+
+    pub fn levels(&self) -> &Vec<f_> {
+        &self.levels
+    }
+}
+
+impl BoundTrait for Ordinal {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn inside(&self, x: &f_) -> bool {
+        x <= &(self.levels.len() as f_ - 1.0) && x >= &0.0
+    }
+
+    /// Index of the closest level.
+    fn snap(&self, x: &f_) -> f_ {
+        x.round().clamp(0.0, self.levels.len() as f_ - 1.0)
+    }
+
+    fn repr(&self, x: &f_) -> Option<BoundRepr> {
+        if self.inside(x) {
+            Some(BoundRepr::Ordinal((
+                self.label.clone(),
+                self.levels[self.snap(x) as usize],
+            )))
+        } else {
+            None
+        }
+    }
+
+    fn parse(&self, x: &BoundRepr) -> f_ {
+        match x {
+            BoundRepr::Ordinal((label, level)) => {
+                if label != self.label() {
+                    panic!(
+                        "Label of bound representaion {} does not match label of bound {}",
+                        x.label(),
+                        label
+                    )
+                };
+                let index = self
+                    .levels
+                    .iter()
+                    .position(|lvl| lvl == level)
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "Level {} could not be found in bound {} during parsing!",
+                            level,
+                            self.label()
+                        )
+                    });
+
+                index as f_
+            }
+            _ => panic!(
+                "Bound representation {} cannot be parsed to ordinal bound type!",
+                x
+            ),
+        }
+    }
+
+    fn enum_var(&self) -> BoundType {
+        BoundType::Ordinal(self.clone())
+    }
+
+    /// Level at the index `x`, interpolated linearly between levels and extrapolated beyond them.
+    fn untransform(&self, x: &f_) -> f_ {
+        let i = (x.floor() as usize).min(self.levels.len() - 2);
+        let (lower, upper) = (self.levels[i], self.levels[i + 1]);
+        lower + (x - i as f_) * (upper - lower)
+    }
+
+    fn bound_arr(&self) -> Array1<f_> {
+        arr1(&[0.0, self.levels.len() as f_ - 1.0])
     }
 }
 
@@ -235,6 +495,10 @@ impl Categorical {
             categories,
         }
     }
+
+    pub fn categories(&self) -> &Vec<String> {
+        &self.categories
+    }
 }
 
 impl BoundTrait for Categorical {
@@ -247,6 +511,11 @@ impl BoundTrait for Categorical {
             || (x.ceil() as i_) == self.categories.len() as i_
     }
 
+    /// Centre of the interval of the category.
+    fn snap(&self, x: &f_) -> f_ {
+        x.floor().clamp(0.0, self.categories.len() as f_ - 1.0) + 0.5
+    }
+
     fn repr(&self, x: &f_) -> Option<BoundRepr> {
         if self.inside(x) {
             let mut index = x.floor() as usize;
@@ -286,7 +555,7 @@ impl BoundTrait for Categorical {
                         )
                     });
 
-                rand::rng().random::<f_>() + index as f_ // TODO: randomize in interval instead of fixed index?
+                index as f_ + 0.5 // centre of the interval of the category
             }
             _ => panic!(
                 "Bound representation {} cannot be parsed to categorical bound type!",
@@ -326,6 +595,11 @@ impl BoundTrait for Boolean {
         ((x.floor() as i_) <= 1 && (x.floor() as i_) >= 0) || (x.ceil() as i_) == 2
     }
 
+    /// Centre of the interval of the value.
+    fn snap(&self, x: &f_) -> f_ {
+        x.floor().clamp(0.0, 1.0) + 0.5
+    }
+
     fn repr(&self, x: &f_) -> Option<BoundRepr> {
         if self.inside(x) {
             let b = x <= &1.0;
@@ -347,8 +621,8 @@ impl BoundTrait for Boolean {
                     )
                 };
                 match bool {
-                    true => rand::rng().random(), //TODO: randomize in interval instead of fixed vals?
-                    false => rand::rng().random::<f_>() + 1.0,
+                    true => 0.5,
+                    false => 1.5,
                 }
             }
             _ => panic!(
@@ -372,6 +646,8 @@ impl BoundTrait for Boolean {
 pub enum BoundType {
     Continuous,
     Discrete,
+    QuantizedContinuous,
+    Ordinal,
     Categorical,
     Boolean,
 }
@@ -383,18 +659,29 @@ impl Display for BoundType {
                 f,
                 "\"{}\" (Continuous): Upper: {:.3}, Lower: {:.3}, Transform: {}",
                 continuous.label(),
-                continuous.transform.inv_transform(continuous.upper),
-                continuous.transform.inv_transform(continuous.lower),
+                continuous.range.1,
+                continuous.range.0,
                 continuous.transform
             ),
             BoundType::Discrete(discrete) => write!(
                 f,
                 "\"{}\" (Discrete): Upper: {}, Lower: {}, Transform: {}",
                 discrete.label(),
-                discrete.transform.inv_transform(discrete.upper as f_) as i_,
-                discrete.transform.inv_transform(discrete.lower as f_) as i_,
+                discrete.upper,
+                discrete.lower,
                 discrete.transform
             ),
+            BoundType::QuantizedContinuous(quantized) => write!(
+                f,
+                "\"{}\" (QuantizedContinuous): Upper: {:.3}, Lower: {:.3}, Step: {}",
+                quantized.label(),
+                quantized.upper,
+                quantized.lower,
+                quantized.step
+            ),
+            BoundType::Ordinal(ordinal) => {
+                write!(f, "\"{}\" (Ordinal): {:?}", ordinal.label(), ordinal.levels)
+            }
             BoundType::Categorical(categorical) => write!(
                 f,
                 "\"{}\" (Categorical): {:?}",
@@ -412,6 +699,7 @@ impl Display for BoundType {
 pub enum BoundRepr {
     Continuous((String, f_)),
     Discrete((String, i_)),
+    Ordinal((String, f_)),
     Categorical((String, String)),
     Boolean((String, bool)),
 }
@@ -421,6 +709,7 @@ impl BoundRepr {
         match self {
             BoundRepr::Continuous((label, _)) => label,
             BoundRepr::Discrete((label, _)) => label,
+            BoundRepr::Ordinal((label, _)) => label,
             BoundRepr::Categorical((label, _)) => label,
             BoundRepr::Boolean((label, _)) => label,
         }
@@ -432,6 +721,7 @@ impl Display for BoundRepr {
         match self {
             BoundRepr::Continuous((label, val)) => write!(f, "{}: {}", label, val)?,
             BoundRepr::Discrete((label, int)) => write!(f, "{}: {}", label, int)?,
+            BoundRepr::Ordinal((label, level)) => write!(f, "{}: {}", label, level)?,
             BoundRepr::Categorical((label, cat)) => write!(f, "{}: {}", label, cat)?,
             BoundRepr::Boolean((label, b)) => write!(f, "{}: {}", label, b)?,
         };
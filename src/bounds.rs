@@ -3,9 +3,13 @@ use std::{fmt::Display, marker::PhantomData, ops::Deref, slice::Iter};
 use ndarray::{Array1, ArrayView1};
 
 pub use crate::{
-    bound_types::{Boolean, BoundRepr, BoundTrait, BoundType, Categorical, Continuous, Discrete},
+    bound_types::{
+        Boolean, BoundRepr, BoundTrait, BoundType, Categorical, Continuous, Discrete, Ordinal,
+        QuantizedContinuous,
+    },
     bounds_array::ArrayBounds,
-    bounds_transforms::BoundTransform,
+    bounds_io::BoundsError,
+    bounds_transforms::{BoundTransform, BoundTransformTrait},
     f_, i_,
 };
 
@@ -23,9 +27,83 @@ impl BoundsConfig for Ready {}
 pub struct Bounds<BoundsConfig = Config> {
     bounds: Vec<BoundType>,
     bounds_arr: ArrayBounds,
+    fidelity: Option<Fidelity>,
+    context: Vec<usize>,
+    conditions: Vec<Condition>,
     config_state: PhantomData<BoundsConfig>,
 }
 
+/// Fidelity parameter of the objective function, with levels ordered from the lowest to the highest fidelity.
+///
+/// The fidelities are modelled with a linear autoregressive (co-kriging) model,
+/// `f_t(x) = rho * f_{t-1}(x) + delta_t(x)`, where every `delta_t` has a variance of `nu`
+/// relative to the lowest fidelity. This is a fixed-correlation approximation: `rho` and `nu` are
+/// inputs of the user and are not learned from the observations or optimised with the
+/// hyperparameters of the kernel, so a poor choice biases the predictions at the highest fidelity.
+/// The resulting covariance between the levels scales the kernel matrix element-wise.
+#[derive(Debug, Clone)]
+pub struct Fidelity {
+    label: String,
+    costs: Vec<f_>,
+    rho: f_,
+    nu: f_,
+}
+
+impl Fidelity {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Evaluation costs of the fidelity levels.
+    pub fn costs(&self) -> &Vec<f_> {
+        &self.costs
+    }
+
+    /// Number of fidelity levels.
+    pub fn n(&self) -> usize {
+        self.costs.len()
+    }
+
+    /// Level of the highest fidelity.
+    pub fn top(&self) -> usize {
+        self.costs.len() - 1
+    }
+
+    pub fn rho(&self) -> f_ {
+        self.rho
+    }
+
+    pub fn nu(&self) -> f_ {
+        self.nu
+    }
+}
+
+/// Condition under which a child parameter is active, i.e. when its parent is active and takes one
+/// of the values of the condition.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    child: usize,
+    parent: usize,
+    values: Vec<f_>,
+}
+
+impl Condition {
+    /// Index of the conditional parameter.
+    pub fn child(&self) -> usize {
+        self.child
+    }
+
+    /// Index of the parameter that the condition depends on.
+    pub fn parent(&self) -> usize {
+        self.parent
+    }
+
+    /// Values of the parent, in the space of the bounds, for which the child is active.
+    pub fn values(&self) -> &Vec<f_> {
+        &self.values
+    }
+}
+
 impl Default for Bounds {
     fn default() -> Self {
         Self::new()
@@ -44,7 +122,23 @@ impl Display for Bounds<Ready> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.bounds
             .iter()
-            .try_for_each(|bound| writeln!(f, "{}", bound))
+            .try_for_each(|bound| writeln!(f, "{}", bound))?;
+
+        self.conditions.iter().try_for_each(|cond| {
+            let parent = &self.bounds[cond.parent];
+            let values: Vec<String> = cond
+                .values
+                .iter()
+                .filter_map(|val| parent.repr(val))
+                .map(|repr| repr.to_string())
+                .collect();
+            writeln!(
+                f,
+                "\"{}\" active if {}",
+                self.bounds[cond.child].label(),
+                values.join(" or ")
+            )
+        })
     }
 }
 
@@ -57,6 +151,9 @@ impl Bounds {
         Bounds {
             bounds,
             bounds_arr,
+            fidelity: None,
+            context: vec![],
+            conditions: vec![],
             config_state: PhantomData,
         }
     }
@@ -75,6 +172,9 @@ impl Bounds {
         Bounds {
             bounds,
             bounds_arr,
+            fidelity: None,
+            context: vec![],
+            conditions: vec![],
             config_state: PhantomData,
         }
     }
@@ -82,7 +182,12 @@ impl Bounds {
 
 impl Bounds<Config> {
     fn push_bound(&mut self, bound: BoundType) {
-        if self.bounds.iter().any(|b| b.label() == bound.label()) {
+        if self.bounds.iter().any(|b| b.label() == bound.label())
+            || self
+                .fidelity
+                .as_ref()
+                .is_some_and(|f| f.label == bound.label())
+        {
             panic!(
                 "Cannot have bounds with duplicate \"{}\" labels!",
                 bound.label()
@@ -162,10 +267,165 @@ impl Bounds<Config> {
         self
     }
 
+    /// Adds a continuous bound that only takes values at multiples of `step` from `lower`.
+    pub fn add_quantized_continuous(
+        mut self,
+        label: &str,
+        upper: f_,
+        lower: f_,
+        step: f_,
+    ) -> Bounds<Config> {
+        if upper <= lower {
+            panic!("Upper value for quantized continuous bound cannot be <= lower value! ")
+        }
+
+        self.push_bound(QuantizedContinuous::new(label, upper, lower, step).enum_var());
+        self
+    }
+
+    /// Adds an ordinal bound over the strictly increasing numeric `levels`.
+    pub fn add_ordinal(mut self, label: &str, levels: Vec<f_>) -> Bounds<Config> {
+        self.push_bound(Ordinal::new(label, levels).enum_var());
+        self
+    }
+
+    /// Adds a context variable, which is observed but cannot be chosen by the algorithm, see
+    /// [`crate::LABCAT::suggest_with_context`].
+    pub fn add_context(mut self, label: &str, upper: f_, lower: f_) -> Bounds<Config> {
+        if upper <= lower {
+            panic!("Upper value for context bound cannot be <= lower value! ")
+        }
+
+        self.push_bound(Continuous::new(label, upper, lower).enum_var());
+        self.context.push(self.bounds.len() - 1);
+        self
+    }
+
+    /// Adds a fidelity parameter with the evaluation costs of the levels, from the lowest to the highest fidelity.
+    pub fn add_fidelity(self, label: &str, costs: Vec<f_>) -> Bounds<Config> {
+        self.add_fidelity_with_correlation(label, costs, 0.9, 0.1)
+    }
+
+    /// Adds a fidelity parameter with the fixed correlation `rho` in (0, 1] and relative discrepancy
+    /// variance `nu` of the autoregressive model between successive levels, see [`Fidelity`].
+    pub fn add_fidelity_with_correlation(
+        mut self,
+        label: &str,
+        costs: Vec<f_>,
+        rho: f_,
+        nu: f_,
+    ) -> Bounds<Config> {
+        if self.fidelity.is_some() {
+            panic!("Bounds can only have a single fidelity parameter!")
+        }
+
+        if self.bounds.iter().any(|b| b.label() == label) {
+            panic!("Cannot have bounds with duplicate \"{}\" labels!", label)
+        }
+
+        if costs.len() < 2 {
+            panic!("Fidelity parameter must have at least two levels!")
+        }
+
+        if costs.iter().any(|c| !(c.is_finite() && *c > 0.0)) {
+            panic!("Fidelity costs must be finite and positive!")
+        }
+
+        if !(rho > 0.0 && rho <= 1.0) {
+            panic!("Fidelity correlation must be in (0, 1]!")
+        }
+
+        if !(nu.is_finite() && nu > 0.0) {
+            panic!("Fidelity discrepancy variance must be finite and positive!")
+        }
+
+        self.fidelity = Some(Fidelity {
+            label: label.into(),
+            costs,
+            rho,
+            nu,
+        });
+        self
+    }
+
+    /// Bound with the label `label`, if it has been added.
+    pub fn bound(&self, label: &str) -> Option<&BoundType> {
+        self.bounds.iter().find(|b| b.label() == label)
+    }
+
+    /// Makes the bound `child` only active when the bound `parent` takes one of `values`, and when
+    /// `parent` is itself active. Inactive parameters are left out of [`BoundReprs`] and fixed at
+    /// an imputed value.
+    pub fn add_condition(
+        mut self,
+        child: &str,
+        parent: &str,
+        values: Vec<BoundRepr>,
+    ) -> Bounds<Config> {
+        let index = |label: &str| {
+            self.bounds
+                .iter()
+                .position(|b| b.label() == label)
+                .unwrap_or_else(|| panic!("Bound \"{}\" could not be found for condition!", label))
+        };
+        let (child, parent) = (index(child), index(parent));
+
+        if child == parent {
+            panic!("Bound cannot be conditional on itself!")
+        }
+
+        if self.conditions.iter().any(|cond| cond.child == child) {
+            panic!(
+                "Bound \"{}\" already has a condition!",
+                self.bounds[child].label()
+            )
+        }
+
+        if self.context.contains(&child) {
+            panic!("Context variables cannot be conditional!")
+        }
+
+        if let BoundType::Continuous(_) = self.bounds[parent] {
+            panic!("Parent of condition cannot be a continuous bound!")
+        }
+
+        if values.is_empty() {
+            panic!("Condition must have at least one value of the parent!")
+        }
+
+        // Walk up the conditions of the parent to check for cycles
+        let mut ancestor = Some(parent);
+        while let Some(dim) = ancestor {
+            if dim == child {
+                panic!("Conditions of bounds cannot be cyclic!")
+            }
+            ancestor = self
+                .conditions
+                .iter()
+                .find(|cond| cond.child == dim)
+                .map(|cond| cond.parent);
+        }
+
+        let values = values
+            .iter()
+            .map(|val| self.bounds[parent].parse(val))
+            .collect();
+
+        self.conditions.push(Condition {
+            child,
+            parent,
+            values,
+        });
+        self
+    }
+
     pub fn build(self) -> Bounds<Ready> {
         Bounds {
             bounds: self.bounds,
             bounds_arr: self.bounds_arr,
+            fidelity: self.fidelity,
+            context: self.context,
+            conditions: self.conditions,
             config_state: PhantomData,
         }
     }
@@ -191,41 +451,166 @@ impl Bounds<Ready> {
             .all(|(bound, x)| bound.inside(x))
     }
 
+    /// Closest point to `x` that the bounds can take, e.g. with integer discrete values and
+    /// inactive parameters at their imputed values.
+    pub fn snap(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        if x.len() != self.bounds.len() {
+            panic!("Input point dim does not match bounds dim!")
+        };
+
+        let x: Array1<f_> = self
+            .bounds
+            .iter()
+            .zip(x.iter())
+            .map(|(bound, x)| bound.snap(x))
+            .collect();
+
+        self.impute(x.view())
+    }
+
+    /// Whether each parameter is active at `x`, according to the conditions of the bounds.
+    pub fn active(&self, x: ArrayView1<f_>) -> Vec<bool> {
+        if x.len() != self.bounds.len() {
+            panic!("Input point dim does not match bounds dim!")
+        };
+
+        (0..self.dim()).map(|i| self.is_active(i, x)).collect()
+    }
+
+    fn is_active(&self, i: usize, x: ArrayView1<f_>) -> bool {
+        match self.conditions.iter().find(|cond| cond.child == i) {
+            Some(cond) => {
+                let parent = &self.bounds[cond.parent];
+                cond.values.contains(&parent.snap(&x[cond.parent]))
+                    && self.is_active(cond.parent, x)
+            }
+            None => true,
+        }
+    }
+
+    /// `x` with the inactive parameters set to their imputed values, the values that the bounds
+    /// can take closest to their midpoints.
+    pub fn impute(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        let mid = self.bounds_arr.midpoint();
+
+        self.active(x)
+            .iter()
+            .zip(self.bounds.iter())
+            .enumerate()
+            .map(|(i, (active, bound))| match active {
+                true => x[i],
+                false => bound.snap(&mid[i]),
+            })
+            .collect()
+    }
+
+    /// Representation of `x`, leaving out the inactive parameters.
     pub fn repr(&self, x: ArrayView1<f_>) -> Option<BoundReprs> {
         if x.len() != self.bounds.len() {
             panic!("Input point dim does not match bounds dim!")
         };
 
+        let active = self.active(x);
         let reprs: Option<Vec<BoundRepr>> = self
             .bounds
             .iter()
             .zip(x.iter())
-            .map(|(bound, x)| bound.repr(x))
+            .zip(active.iter())
+            .filter(|(_, active)| **active)
+            .map(|((bound, x), _)| bound.repr(x))
             .collect();
 
         reprs.map(|reprs| BoundReprs { reprs })
     }
 
+    /// Point of the representation `x`, where inactive parameters may be left out.
     pub fn parse(&self, x: BoundReprs) -> Array1<f_> {
+        let mid = self.bounds_arr.midpoint();
         let mut res_vec = vec![];
+        let mut missing = vec![];
 
-        for bound in self.bounds.iter() {
+        for (i, bound) in self.bounds.iter().enumerate() {
             // iter through self bounds
             let x_match = x
                 .iter() // try to find matching boundrepr in x
-                .find(|&bound_repr| bound_repr.label() == bound.label())
-                .unwrap_or_else(|| {
-                    panic!("Bound {} could not be found during parsing!", bound.label())
-                });
-            res_vec.push(bound.parse(x_match)); // parse matching bound_repr and push into res
+                .find(|&bound_repr| bound_repr.label() == bound.label());
+            match x_match {
+                Some(x_match) => res_vec.push(bound.parse(x_match)), // parse matching bound_repr and push into res
+                None => {
+                    res_vec.push(bound.snap(&mid[i]));
+                    missing.push(i);
+                }
+            }
         }
 
-        Array1::from_vec(res_vec)
+        let res = Array1::from_vec(res_vec);
+        let active = self.active(res.view());
+        if let Some(i) = missing.iter().find(|i| active[**i]) {
+            panic!(
+                "Bound {} could not be found during parsing!",
+                self.bounds[*i].label()
+            )
+        }
+
+        self.impute(res.view())
     }
 
     pub fn bounds_arr(&self) -> &ArrayBounds {
         &self.bounds_arr
     }
+
+    /// Point `x` in the original units of the bounds, without snapping.
+    pub fn untransform(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        if x.len() != self.bounds.len() {
+            panic!("Input point dim does not match bounds dim!")
+        };
+
+        self.bounds
+            .iter()
+            .zip(x.iter())
+            .map(|(bound, x)| bound.untransform(x))
+            .collect()
+    }
+
+    /// Rate of change of each coordinate in the original units of the bounds with respect to its
+    /// transformed value at `x`, which converts short lengths in transformed space to original units.
+    pub fn untransform_rate(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        if x.len() != self.bounds.len() {
+            panic!("Input point dim does not match bounds dim!")
+        };
+
+        self.bounds
+            .iter()
+            .zip(x.iter())
+            .map(|(bound, x)| {
+                let h = f_::EPSILON.cbrt() * x.abs().max(1.0);
+                (bound.untransform(&(x + h)) - bound.untransform(&(x - h))).abs() / (2.0 * h)
+            })
+            .collect()
+    }
+
+    pub fn fidelity(&self) -> Option<&Fidelity> {
+        self.fidelity.as_ref()
+    }
+
+    pub fn conditions(&self) -> &Vec<Condition> {
+        &self.conditions
+    }
+
+    /// Indices of the context variables, in the order that they were added.
+    pub fn context_dims(&self) -> &Vec<usize> {
+        &self.context
+    }
+
+    /// Indices of the categorical and boolean bounds.
+    pub fn categorical_dims(&self) -> Vec<usize> {
+        self.bounds
+            .iter()
+            .enumerate()
+            .filter(|(_, bound)| matches!(bound, BoundType::Categorical(_) | BoundType::Boolean(_)))
+            .map(|(i, _)| i)
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -241,6 +626,46 @@ impl BoundReprs {
     pub fn iter(&self) -> Iter<BoundRepr> {
         self.reprs.iter()
     }
+
+    /// Representation of the bound `label`, or `None` if it is not present, e.g. when inactive.
+    pub fn get(&self, label: &str) -> Option<&BoundRepr> {
+        self.reprs.iter().find(|repr| repr.label() == label)
+    }
+
+    /// Value of a continuous, discrete or ordinal bound.
+    #[allow(clippy::unnecessary_cast)] // f_ and i_ depend on the f64 feature
+    pub fn get_f64(&self, label: &str) -> Option<f64> {
+        match self.get(label)? {
+            BoundRepr::Continuous((_, val)) | BoundRepr::Ordinal((_, val)) => Some(*val as f64),
+            BoundRepr::Discrete((_, val)) => Some(*val as f64),
+            _ => None,
+        }
+    }
+
+    /// Value of a discrete bound.
+    #[allow(clippy::unnecessary_cast)] // i_ depends on the f64 feature
+    pub fn get_i64(&self, label: &str) -> Option<i64> {
+        match self.get(label)? {
+            BoundRepr::Discrete((_, val)) => Some(*val as i64),
+            _ => None,
+        }
+    }
+
+    /// Category of a categorical bound.
+    pub fn get_str(&self, label: &str) -> Option<&str> {
+        match self.get(label)? {
+            BoundRepr::Categorical((_, cat)) => Some(cat),
+            _ => None,
+        }
+    }
+
+    /// Value of a boolean bound.
+    pub fn get_bool(&self, label: &str) -> Option<bool> {
+        match self.get(label)? {
+            BoundRepr::Boolean((_, b)) => Some(*b),
+            _ => None,
+        }
+    }
 }
 
 // impl Deref for BoundReprs {
@@ -2,12 +2,46 @@ use egobox_doe::{Lhs, LhsKind, SamplingMethod};
 use ndarray::{Array1, Array2, ArrayView1, Axis, array};
 use ndarray_rand::rand_distr::num_traits::Zero;
 use rand::Rng;
+use sobol::Sobol;
+use sobol::params::JoeKuoD6;
 
 use crate::{
     bound_types::{BoundTrait, BoundType},
     f_,
 };
 
+/// Sampling scheme used to generate the initial design of the algorithm.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InitialDesign {
+    Lhs,
+    Random,
+    Sobol,
+    Maximin,
+}
+
+impl Default for InitialDesign {
+    /// Selected by the `LHS` feature for backwards compatibility.
+    fn default() -> Self {
+        if cfg!(feature = "LHS") {
+            InitialDesign::Lhs
+        } else {
+            InitialDesign::Random
+        }
+    }
+}
+
+impl InitialDesign {
+    pub fn parse_design(data: &str) -> Option<InitialDesign> {
+        match data {
+            "lhs" => Some(InitialDesign::Lhs),
+            "random" => Some(InitialDesign::Random),
+            "sobol" => Some(InitialDesign::Sobol),
+            "maximin" => Some(InitialDesign::Maximin),
+            &_ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ArrayBounds {
     bounds_arr: Array2<f_>,
@@ -47,6 +81,13 @@ impl ArrayBounds {
         &self.bounds_arr
     }
 
+    /// Bounds of the dimensions at `dims` only.
+    pub fn select_dims(&self, dims: &[usize]) -> ArrayBounds {
+        ArrayBounds {
+            bounds_arr: self.bounds_arr.select(Axis(0), dims),
+        }
+    }
+
     pub fn midpoint(&self) -> Array1<f_> {
         let b = &self.bounds_arr;
         Array1::from_shape_fn((b.nrows(),), |i| (b.row(i)[1] + b.row(i)[0]) / 2.0)
@@ -63,12 +104,16 @@ impl ArrayBounds {
             panic!("Input point dim does not match bounds dim!")
         };
 
+        // Tolerance for points on the bounds that pick up round-off error from the memory transform
         self.bounds_arr()
             .view()
             .rows()
             .into_iter()
             .zip(x.iter())
-            .all(|(bound, x)| x <= &bound[1] && x >= &bound[0])
+            .all(|(bound, x)| {
+                let tol = (bound[1] - bound[0]).abs() * 1e-9;
+                x <= &(bound[1] + tol) && x >= &(bound[0] - tol)
+            })
     }
 
     pub fn random_sample(&self, n: usize) -> Array2<f_> {
@@ -87,4 +132,41 @@ impl ArrayBounds {
                 .reversed_axes()
         }
     }
+
+    pub fn maximin_sample(&self, n: usize) -> Array2<f_> {
+        if n.is_zero() {
+            Array2::zeros((self.bounds_arr.nrows(), 0))
+        } else {
+            Lhs::new(self.bounds_arr())
+                .kind(LhsKind::Maximin)
+                .sample(n)
+                .reversed_axes()
+        }
+    }
+
+    pub fn sobol_sample(&self, n: usize) -> Array2<f_> {
+        let mut X = Array2::zeros((self.bounds_arr.nrows(), n));
+
+        // skip the first point of the sequence, which always lies on the lower bounds
+        Sobol::<f_>::new(self.dim(), &JoeKuoD6::minimal())
+            .skip(1)
+            .zip(X.columns_mut())
+            .for_each(|(pt, mut col)| {
+                col.iter_mut()
+                    .zip(pt.iter())
+                    .zip(self.bounds_arr.rows())
+                    .for_each(|((x, u), b)| *x = b[0] + u * (b[1] - b[0]))
+            });
+
+        X
+    }
+
+    pub fn sample(&self, n: usize, design: &InitialDesign) -> Array2<f_> {
+        match design {
+            InitialDesign::Lhs => self.LHS_sample(n),
+            InitialDesign::Random => self.random_sample(n),
+            InitialDesign::Sobol => self.sobol_sample(n),
+            InitialDesign::Maximin => self.maximin_sample(n),
+        }
+    }
 }
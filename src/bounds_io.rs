@@ -0,0 +1,635 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    bound_types::{BoundRepr, BoundTrait, BoundType},
+    bounds::{Bounds, Config, Ready},
+    bounds_transforms::{BoundTransform, BoundTransformType},
+    f_, i_,
+};
+
+/// Error raised when reading or writing bounds from a file.
+#[derive(Debug, Error)]
+pub enum BoundsError {
+    #[error("Could not access bounds file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Bounds file extension \"{0}\" is not supported, expected toml, json, yaml or yml!")]
+    Extension(String),
+    #[error("Could not parse bounds from {format}: {msg}")]
+    Parse { format: &'static str, msg: String },
+    #[error("Could not write bounds to {format}: {msg}")]
+    Write { format: &'static str, msg: String },
+    #[error("Invalid bound \"{label}\": {msg}")]
+    Bound { label: String, msg: String },
+    #[error("Invalid condition of bound \"{child}\": {msg}")]
+    Condition { child: String, msg: String },
+    #[error("Invalid fidelity parameter \"{label}\": {msg}")]
+    Fidelity { label: String, msg: String },
+}
+
+/// File representation of [`Bounds`], using the same bound types as the python bounds config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BoundsSpec {
+    bounds: Vec<BoundSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conditions: Vec<ConditionSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fidelity: Option<FidelitySpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum BoundSpec {
+    Real {
+        label: String,
+        range: (f_, f_),
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        space: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        step: Option<f_>,
+    },
+    Int {
+        label: String,
+        range: (i_, i_),
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        space: Option<String>,
+    },
+    Ord {
+        label: String,
+        levels: Vec<f_>,
+    },
+    Cat {
+        label: String,
+        categories: Vec<String>,
+    },
+    Bool {
+        label: String,
+    },
+    Context {
+        label: String,
+        range: (f_, f_),
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConditionSpec {
+    child: String,
+    parent: String,
+    values: Vec<ValueSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ValueSpec {
+    Bool(bool),
+    Int(i_),
+    Float(f_),
+    Str(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FidelitySpec {
+    label: String,
+    costs: Vec<f_>,
+    #[serde(default = "default_rho")]
+    rho: f_,
+    #[serde(default = "default_nu")]
+    nu: f_,
+}
+
+fn default_rho() -> f_ {
+    0.9
+}
+
+fn default_nu() -> f_ {
+    0.1
+}
+
+impl BoundSpec {
+    fn label(&self) -> &str {
+        match self {
+            BoundSpec::Real { label, .. }
+            | BoundSpec::Int { label, .. }
+            | BoundSpec::Ord { label, .. }
+            | BoundSpec::Cat { label, .. }
+            | BoundSpec::Bool { label }
+            | BoundSpec::Context { label, .. } => label,
+        }
+    }
+
+    /// Checks the values of the bound, which would otherwise panic when added to the bounds.
+    fn validate(&self) -> Result<(), String> {
+        let transform = |space: &Option<String>| match space {
+            Some(space) => BoundTransform::parse_transform(space)
+                .map(Some)
+                .ok_or(format!("space \"{}\" is not supported", space)),
+            None => Ok(None),
+        };
+
+        match self {
+            BoundSpec::Real {
+                range, space, step, ..
+            } => {
+                if !range.0.is_finite() || !range.1.is_finite() {
+                    return Err("range must be finite".into());
+                }
+                if range.1 <= range.0 {
+                    return Err("upper value of range cannot be <= lower value".into());
+                }
+                match (transform(space)?, step) {
+                    (Some(trans), Some(_)) if !matches!(trans, BoundTransform::Linear) => {
+                        Err("step is only supported in linear space".into())
+                    }
+                    (_, Some(step)) if !(*step > 0.0 && *step <= range.1 - range.0) => {
+                        Err("step must be positive and not larger than the range".into())
+                    }
+                    (Some(trans), None) => {
+                        trans.new_transform().transform_range(*range).map(|_| ())
+                    }
+                    _ => Ok(()),
+                }
+            }
+            BoundSpec::Int { range, space, .. } => {
+                if range.1 <= range.0 {
+                    return Err("upper value of range cannot be <= lower value".into());
+                }
+                match transform(space)? {
+                    Some(BoundTransform::Logistic) => {
+                        Err("logistic space is not supported for int bounds".into())
+                    }
+                    Some(trans) => trans
+                        .new_transform()
+                        .transform_range((range.0 as f_, range.1 as f_))
+                        .map(|_| ()),
+                    None => Ok(()),
+                }
+            }
+            BoundSpec::Ord { levels, .. } => {
+                if levels.len() < 2 {
+                    Err("at least two levels are required".into())
+                } else if levels.iter().any(|level| !level.is_finite()) {
+                    Err("levels must be finite".into())
+                } else if levels.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    Err("levels must be strictly increasing".into())
+                } else {
+                    Ok(())
+                }
+            }
+            BoundSpec::Cat { categories, .. } => {
+                if categories.is_empty() {
+                    Err("at least one category is required".into())
+                } else if categories.iter().collect::<HashSet<_>>().len() != categories.len() {
+                    Err("categories must be unique".into())
+                } else {
+                    Ok(())
+                }
+            }
+            BoundSpec::Bool { .. } => Ok(()),
+            BoundSpec::Context { range, .. } => {
+                if !range.0.is_finite() || !range.1.is_finite() {
+                    Err("range must be finite".into())
+                } else if range.1 <= range.0 {
+                    Err("upper value of range cannot be <= lower value".into())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn add_to(&self, bounds: Bounds<Config>) -> Bounds<Config> {
+        let space = |space: &Option<String>| match space {
+            Some(space) => BoundTransform::parse_transform(space).expect("checked by validate"),
+            None => BoundTransform::Linear,
+        };
+
+        match self {
+            BoundSpec::Real {
+                label,
+                range,
+                step: Some(step),
+                ..
+            } => bounds.add_quantized_continuous(label, range.1, range.0, *step),
+            BoundSpec::Real {
+                label,
+                range,
+                space: s,
+                step: None,
+            } => bounds.add_continuous_with_transform(label, range.1, range.0, space(s)),
+            BoundSpec::Int {
+                label,
+                range,
+                space: s,
+            } => bounds.add_discrete_with_transform(label, range.1, range.0, space(s)),
+            BoundSpec::Ord { label, levels } => bounds.add_ordinal(label, levels.clone()),
+            BoundSpec::Cat { label, categories } => {
+                bounds.add_categorical(label, categories.iter().map(|c| c.as_str()).collect())
+            }
+            BoundSpec::Bool { label } => bounds.add_boolean(label),
+            BoundSpec::Context { label, range } => bounds.add_context(label, range.1, range.0),
+        }
+    }
+
+    /// File representation of `bound`, which fails for custom transforms.
+    fn from_bound(bound: &BoundType, context: bool) -> Result<BoundSpec, String> {
+        let label = bound.label().to_string();
+        let space = |transform: &BoundTransformType| match transform {
+            BoundTransformType::Linear(_) => Ok(None),
+            BoundTransformType::Custom(custom) => Err(format!(
+                "custom transform \"{}\" of bound \"{}\" cannot be written",
+                custom.name(),
+                bound.label()
+            )),
+            transform => Ok(Some(transform.name())),
+        };
+
+        let spec = match bound {
+            BoundType::Continuous(cont) if context => BoundSpec::Context {
+                label,
+                range: cont.range(),
+            },
+            BoundType::Continuous(cont) => BoundSpec::Real {
+                label,
+                range: cont.range(),
+                space: space(cont.transform())?,
+                step: None,
+            },
+            BoundType::QuantizedContinuous(quant) => BoundSpec::Real {
+                label,
+                range: quant.range(),
+                space: None,
+                step: Some(quant.step()),
+            },
+            BoundType::Discrete(disc) => BoundSpec::Int {
+                label,
+                range: disc.range(),
+                space: space(disc.transform())?,
+            },
+            BoundType::Ordinal(ord) => BoundSpec::Ord {
+                label,
+                levels: ord.levels().clone(),
+            },
+            BoundType::Categorical(cat) => BoundSpec::Cat {
+                label,
+                categories: cat.categories().clone(),
+            },
+            BoundType::Boolean(_) => BoundSpec::Bool { label },
+        };
+
+        Ok(spec)
+    }
+}
+
+impl ValueSpec {
+    /// Representation of the value for the bound `parent`, if the bound can take the value.
+    fn repr(&self, parent: &BoundType) -> Option<BoundRepr> {
+        let label = parent.label().to_string();
+
+        match (parent, self) {
+            (BoundType::Discrete(disc), ValueSpec::Int(val)) => {
+                let (lower, upper) = disc.range();
+                (*val >= lower && *val <= upper).then_some(BoundRepr::Discrete((label, *val)))
+            }
+            (BoundType::QuantizedContinuous(quant), ValueSpec::Int(_) | ValueSpec::Float(_)) => {
+                let val = self.as_float();
+                let on_step = (parent.snap(&val) - val).abs() <= quant.tol();
+                (parent.inside(&val) && on_step).then_some(BoundRepr::Continuous((label, val)))
+            }
+            (BoundType::Ordinal(ord), ValueSpec::Int(_) | ValueSpec::Float(_)) => {
+                let val = self.as_float();
+                ord.levels()
+                    .contains(&val)
+                    .then_some(BoundRepr::Ordinal((label, val)))
+            }
+            (BoundType::Categorical(cat), ValueSpec::Str(val)) => cat
+                .categories()
+                .contains(val)
+                .then(|| BoundRepr::Categorical((label, val.clone()))),
+            (BoundType::Boolean(_), ValueSpec::Bool(b)) => Some(BoundRepr::Boolean((label, *b))),
+            _ => None,
+        }
+    }
+
+    fn as_float(&self) -> f_ {
+        match self {
+            ValueSpec::Int(val) => *val as f_,
+            ValueSpec::Float(val) => *val,
+            _ => f_::NAN,
+        }
+    }
+
+    fn from_repr(repr: BoundRepr) -> ValueSpec {
+        match repr {
+            BoundRepr::Continuous((_, val)) | BoundRepr::Ordinal((_, val)) => ValueSpec::Float(val),
+            BoundRepr::Discrete((_, val)) => ValueSpec::Int(val),
+            BoundRepr::Categorical((_, cat)) => ValueSpec::Str(cat),
+            BoundRepr::Boolean((_, b)) => ValueSpec::Bool(b),
+        }
+    }
+}
+
+impl BoundsSpec {
+    fn build(&self) -> Result<Bounds<Ready>, BoundsError> {
+        let mut labels = HashSet::new();
+        let mut bounds = Bounds::new();
+
+        for spec in self.bounds.iter() {
+            let label = spec.label().to_string();
+            if !labels.insert(label.clone()) {
+                return Err(BoundsError::Bound {
+                    label,
+                    msg: "label is not unique".into(),
+                });
+            }
+            spec.validate()
+                .map_err(|msg| BoundsError::Bound { label, msg })?;
+
+            bounds = spec.add_to(bounds);
+        }
+
+        if let Some(fid) = &self.fidelity {
+            let err = |msg: &str| {
+                Err(BoundsError::Fidelity {
+                    label: fid.label.clone(),
+                    msg: msg.into(),
+                })
+            };
+
+            if labels.contains(&fid.label) {
+                return err("label is not unique");
+            }
+            if fid.costs.len() < 2 {
+                return err("at least two levels are required");
+            }
+            if fid.costs.iter().any(|c| !(c.is_finite() && *c > 0.0)) {
+                return err("costs must be finite and positive");
+            }
+            if !(fid.rho > 0.0 && fid.rho <= 1.0) {
+                return err("correlation rho must be in (0, 1]");
+            }
+            if !(fid.nu.is_finite() && fid.nu > 0.0) {
+                return err("discrepancy variance nu must be finite and positive");
+            }
+
+            bounds = bounds.add_fidelity_with_correlation(
+                &fid.label,
+                fid.costs.clone(),
+                fid.rho,
+                fid.nu,
+            );
+        }
+
+        let mut children = HashSet::new();
+        for cond in self.conditions.iter() {
+            let err = |msg: String| {
+                Err(BoundsError::Condition {
+                    child: cond.child.clone(),
+                    msg,
+                })
+            };
+            let index = |label: &str| self.bounds.iter().position(|b| b.label() == label);
+
+            let (child, parent) = match (index(&cond.child), index(&cond.parent)) {
+                (Some(child), Some(parent)) => (child, parent),
+                (None, _) => return err("bound could not be found".into()),
+                (_, None) => return err(format!("parent \"{}\" could not be found", cond.parent)),
+            };
+
+            if child == parent {
+                return err("bound cannot be conditional on itself".into());
+            }
+            if !children.insert(child) {
+                return err("bound has more than one condition".into());
+            }
+            if let BoundSpec::Context { .. } = self.bounds[child] {
+                return err("context variables cannot be conditional".into());
+            }
+
+            // Walk up the conditions of the parent to check for cycles
+            let mut ancestor = Some(cond.parent.as_str());
+            while let Some(label) = ancestor {
+                if label == cond.child {
+                    return err("conditions cannot be cyclic".into());
+                }
+                ancestor = self
+                    .conditions
+                    .iter()
+                    .find(|c| c.child == label)
+                    .map(|c| c.parent.as_str());
+            }
+
+            let parent = bounds.bound(&cond.parent).expect("bound has been added");
+            if let BoundType::Continuous(_) = parent {
+                return err("parent cannot be a continuous bound".into());
+            }
+            if cond.values.is_empty() {
+                return err("at least one value of the parent is required".into());
+            }
+
+            let values: Option<Vec<BoundRepr>> =
+                cond.values.iter().map(|val| val.repr(parent)).collect();
+            match values {
+                Some(values) => bounds = bounds.add_condition(&cond.child, &cond.parent, values),
+                None => {
+                    return err(format!(
+                        "values could not be parsed for parent \"{}\"",
+                        cond.parent
+                    ));
+                }
+            }
+        }
+
+        Ok(bounds.build())
+    }
+
+    fn from_bounds(bounds: &Bounds<Ready>) -> Result<BoundsSpec, String> {
+        let specs = bounds
+            .iter_bounds()
+            .enumerate()
+            .map(|(i, bound)| BoundSpec::from_bound(bound, bounds.context_dims().contains(&i)))
+            .collect::<Result<_, _>>()?;
+
+        let bound_types: Vec<&BoundType> = bounds.iter_bounds().collect();
+        let conditions = bounds
+            .conditions()
+            .iter()
+            .map(|cond| {
+                let parent = bound_types[cond.parent()];
+                ConditionSpec {
+                    child: bound_types[cond.child()].label().to_string(),
+                    parent: parent.label().to_string(),
+                    values: cond
+                        .values()
+                        .iter()
+                        .filter_map(|val| parent.repr(val))
+                        .map(ValueSpec::from_repr)
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let fidelity = bounds.fidelity().map(|fid| FidelitySpec {
+            label: fid.label().to_string(),
+            costs: fid.costs().clone(),
+            rho: fid.rho(),
+            nu: fid.nu(),
+        });
+
+        Ok(BoundsSpec {
+            bounds: specs,
+            conditions,
+            fidelity,
+        })
+    }
+}
+
+impl Bounds {
+    pub fn from_toml(data: &str) -> Result<Bounds<Ready>, BoundsError> {
+        let spec: BoundsSpec = toml::from_str(data).map_err(|err| BoundsError::Parse {
+            format: "TOML",
+            msg: err.to_string(),
+        })?;
+        spec.build()
+    }
+
+    pub fn from_json(data: &str) -> Result<Bounds<Ready>, BoundsError> {
+        let spec: BoundsSpec = serde_json::from_str(data).map_err(|err| BoundsError::Parse {
+            format: "JSON",
+            msg: err.to_string(),
+        })?;
+        spec.build()
+    }
+
+    pub fn from_yaml(data: &str) -> Result<Bounds<Ready>, BoundsError> {
+        let spec: BoundsSpec = serde_yaml::from_str(data).map_err(|err| BoundsError::Parse {
+            format: "YAML",
+            msg: err.to_string(),
+        })?;
+        spec.build()
+    }
+
+    /// Reads bounds from a TOML, JSON or YAML file, selected by the extension of `path`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Bounds<Ready>, BoundsError> {
+        let data = fs::read_to_string(&path)?;
+
+        match extension(path.as_ref()).as_str() {
+            "toml" => Bounds::from_toml(&data),
+            "json" => Bounds::from_json(&data),
+            "yaml" | "yml" => Bounds::from_yaml(&data),
+            ext => Err(BoundsError::Extension(ext.into())),
+        }
+    }
+}
+
+impl Bounds<Ready> {
+    fn spec(&self, format: &'static str) -> Result<BoundsSpec, BoundsError> {
+        BoundsSpec::from_bounds(self).map_err(|msg| BoundsError::Write { format, msg })
+    }
+
+    pub fn to_toml(&self) -> Result<String, BoundsError> {
+        toml::to_string(&self.spec("TOML")?).map_err(|err| BoundsError::Write {
+            format: "TOML",
+            msg: err.to_string(),
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, BoundsError> {
+        serde_json::to_string_pretty(&self.spec("JSON")?).map_err(|err| BoundsError::Write {
+            format: "JSON",
+            msg: err.to_string(),
+        })
+    }
+
+    pub fn to_yaml(&self) -> Result<String, BoundsError> {
+        serde_yaml::to_string(&self.spec("YAML")?).map_err(|err| BoundsError::Write {
+            format: "YAML",
+            msg: err.to_string(),
+        })
+    }
+
+    /// Writes the bounds to a TOML, JSON or YAML file, selected by the extension of `path`.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), BoundsError> {
+        let data = match extension(path.as_ref()).as_str() {
+            "toml" => self.to_toml()?,
+            "json" => self.to_json()?,
+            "yaml" | "yml" => self.to_yaml()?,
+            ext => return Err(BoundsError::Extension(ext.into())),
+        };
+
+        Ok(fs::write(path, data)?)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Message of the error raised when reading a file with the single real bound `bound`.
+    fn bound_error(bound: &str) -> String {
+        let data = format!("[[bounds]]\ntype = \"real\"\nlabel = \"x\"\n{}\n", bound);
+        match Bounds::from_toml(&data) {
+            Err(BoundsError::Bound { label, msg }) if label == "x" => msg,
+            Err(err) => panic!("unexpected error for {}: {}", bound, err),
+            Ok(_) => panic!("bound {} should be rejected", bound),
+        }
+    }
+
+    #[test]
+    fn non_finite_power() {
+        for space in ["power(inf)", "power(nan)"] {
+            let msg = bound_error(&format!("range = [1.0, 2.0]\nspace = \"{}\"", space));
+            assert!(msg.contains("not supported"), "{}", msg);
+        }
+    }
+
+    #[test]
+    fn non_finite_affine() {
+        for space in ["affine(nan, 0)", "affine(1, inf)"] {
+            let msg = bound_error(&format!("range = [1.0, 2.0]\nspace = \"{}\"", space));
+            assert!(msg.contains("not supported"), "{}", msg);
+        }
+    }
+
+    #[test]
+    fn non_finite_range() {
+        for range in ["[nan, 1.0]", "[0.0, nan]", "[-inf, 1.0]", "[0.0, inf]"] {
+            let msg = bound_error(&format!("range = {}", range));
+            assert_eq!(msg, "range must be finite");
+        }
+    }
+
+    #[test]
+    fn non_finite_step() {
+        let msg = bound_error("range = [0.0, 1.0]\nstep = nan");
+        assert_eq!(msg, "step must be positive and not larger than the range");
+    }
+
+    #[test]
+    fn invalid_rho() {
+        for rho in ["nan", "inf", "-0.5", "0.0", "1.5"] {
+            let data = format!(
+                "[[bounds]]\ntype = \"real\"\nlabel = \"x\"\nrange = [0.0, 1.0]\n\n\
+                 [fidelity]\nlabel = \"f\"\ncosts = [1.0, 10.0]\nrho = {}\n",
+                rho
+            );
+            match Bounds::from_toml(&data) {
+                Err(BoundsError::Fidelity { label, msg }) if label == "f" => {
+                    assert_eq!(msg, "correlation rho must be in (0, 1]")
+                }
+                Err(err) => panic!("unexpected error for rho = {}: {}", rho, err),
+                Ok(_) => panic!("rho = {} should be rejected", rho),
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use std::fmt::{Debug, Display};
 use std::ops::Neg;
+use std::sync::Arc;
 
 use enum_dispatch::enum_dispatch;
 
@@ -7,8 +9,20 @@ use crate::f_;
 pub enum BoundTransform {
     Linear,
     Log,
+    Log10,
     Logistic,
     BiLog,
+    Sqrt,
+    /// `x^p`, with `p != 0`.
+    Power(f_),
+    Reciprocal,
+    /// `scale * x + offset`, with `scale != 0`.
+    Affine {
+        scale: f_,
+        offset: f_,
+    },
+    /// User-defined transform, see [`BoundTransform::custom`].
+    Custom(Custom),
 }
 
 impl BoundTransform {
@@ -16,35 +30,100 @@ impl BoundTransform {
         match self {
             BoundTransform::Linear => BoundTransformType::Linear(Linear {}),
             BoundTransform::Log => BoundTransformType::Log(Log {}),
+            BoundTransform::Log10 => BoundTransformType::Log10(Log10 {}),
             BoundTransform::Logistic => BoundTransformType::Logistic(Logistic {}),
             BoundTransform::BiLog => BoundTransformType::BiLog(BiLog {}),
+            BoundTransform::Sqrt => BoundTransformType::Sqrt(Sqrt {}),
+            BoundTransform::Power(p) => {
+                if p == 0.0 || !p.is_finite() {
+                    panic!("Exponent of power bound transformation must be finite and != 0!");
+                }
+                BoundTransformType::Power(Power { p })
+            }
+            BoundTransform::Reciprocal => BoundTransformType::Reciprocal(Reciprocal {}),
+            BoundTransform::Affine { scale, offset } => {
+                if scale == 0.0 || !scale.is_finite() || !offset.is_finite() {
+                    panic!("Scale of affine bound transformation must be finite and != 0!");
+                }
+                BoundTransformType::Affine(Affine { scale, offset })
+            }
+            BoundTransform::Custom(custom) => BoundTransformType::Custom(custom),
         }
     }
 
+    /// User-defined transform named `name`, which must be monotonic over the range of the bounds
+    /// it is used for. Bounds with custom transforms cannot be written to a file.
+    pub fn custom<T: BoundTransformTrait + Send + Sync + 'static>(
+        name: &str,
+        transform: T,
+    ) -> BoundTransform {
+        BoundTransform::Custom(Custom {
+            name: name.into(),
+            transform: Arc::new(transform),
+        })
+    }
+
+    /// Parses the name of a transform, with the arguments of parameterised transforms given in
+    /// brackets, e.g. `power(0.5)` or `affine(2, -1)`.
     pub fn parse_transform(data: &str) -> Option<BoundTransform> {
         match data {
             "linear" => Some(BoundTransform::Linear),
             "log" => Some(BoundTransform::Log),
+            "log10" => Some(BoundTransform::Log10),
             "bilog" => Some(BoundTransform::BiLog),
             "logistic" | "logit" => Some(BoundTransform::Logistic),
-            &_ => None,
+            "sqrt" => Some(BoundTransform::Sqrt),
+            "reciprocal" => Some(BoundTransform::Reciprocal),
+            &_ => match (
+                parse_args(data, "power").as_deref(),
+                parse_args(data, "affine").as_deref(),
+            ) {
+                (Some([p]), _) if *p != 0.0 => Some(BoundTransform::Power(*p)),
+                (_, Some([scale, offset])) if *scale != 0.0 => Some(BoundTransform::Affine {
+                    scale: *scale,
+                    offset: *offset,
+                }),
+                _ => None,
+            },
         }
     }
+}
 
-    pub fn to_string(&self) -> &str {
+impl Display for BoundTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            BoundTransform::Linear => "linear",
-            BoundTransform::Log => "log",
-            BoundTransform::Logistic => "logistic",
-            BoundTransform::BiLog => "bilog",
+            BoundTransform::Linear => write!(f, "linear"),
+            BoundTransform::Log => write!(f, "log"),
+            BoundTransform::Log10 => write!(f, "log10"),
+            BoundTransform::Logistic => write!(f, "logistic"),
+            BoundTransform::BiLog => write!(f, "bilog"),
+            BoundTransform::Sqrt => write!(f, "sqrt"),
+            BoundTransform::Power(p) => write!(f, "power({})", p),
+            BoundTransform::Reciprocal => write!(f, "reciprocal"),
+            BoundTransform::Affine { scale, offset } => write!(f, "affine({}, {})", scale, offset),
+            BoundTransform::Custom(custom) => write!(f, "{}", custom.name),
         }
     }
 }
 
+/// Finite numeric arguments of `data` of the form `name(arg, ...)`.
+fn parse_args(data: &str, name: &str) -> Option<Vec<f_>> {
+    data.strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .split(',')
+        .map(|arg| arg.trim().parse().ok().filter(|arg: &f_| arg.is_finite()))
+        .collect()
+}
+
 #[enum_dispatch(BoundTransformType)]
 pub trait BoundTransformTrait {
     fn transform(&self, x: f_) -> f_;
     fn inv_transform(&self, x: f_) -> f_;
+    /// Whether `x` is inside the domain of the transform.
+    fn valid(&self, _x: f_) -> bool {
+        true
+    }
 }
 
 #[enum_dispatch]
@@ -52,17 +131,81 @@ pub trait BoundTransformTrait {
 pub enum BoundTransformType {
     Linear,
     Log,
+    Log10,
     Logistic,
     BiLog,
+    Sqrt,
+    Power,
+    Reciprocal,
+    Affine,
+    Custom,
+}
+
+impl BoundTransformType {
+    /// Name of the transform, as parsed by [`BoundTransform::parse_transform`].
+    pub fn name(&self) -> String {
+        match self {
+            BoundTransformType::Linear(_) => "linear".into(),
+            BoundTransformType::Log(_) => "log".into(),
+            BoundTransformType::Log10(_) => "log10".into(),
+            BoundTransformType::Logistic(_) => "logistic".into(),
+            BoundTransformType::BiLog(_) => "bilog".into(),
+            BoundTransformType::Sqrt(_) => "sqrt".into(),
+            BoundTransformType::Power(power) => format!("power({})", power.p),
+            BoundTransformType::Reciprocal(_) => "reciprocal".into(),
+            BoundTransformType::Affine(affine) => {
+                format!("affine({}, {})", affine.scale, affine.offset)
+            }
+            BoundTransformType::Custom(custom) => custom.name.clone(),
+        }
+    }
+
+    /// Transformed lower and upper values of the untransformed `range`, swapped for decreasing
+    /// transforms. Fails if the range is outside the domain of the transform or the transform is
+    /// not strictly monotonic over the range.
+    pub fn transform_range(&self, range: (f_, f_)) -> Result<(f_, f_), String> {
+        let xs: Vec<f_> = (0..8)
+            .map(|i| range.0 + (range.1 - range.0) * i as f_ / 8.0)
+            .chain([range.1])
+            .collect();
+
+        if !xs.iter().all(|x| self.valid(*x)) {
+            return Err(format!(
+                "range is outside the domain of the {} transform",
+                self.name()
+            ));
+        }
+
+        let ts: Vec<f_> = xs.iter().map(|x| self.transform(*x)).collect();
+        let increasing = ts.windows(2).all(|pair| pair[0] < pair[1]);
+        let decreasing = ts.windows(2).all(|pair| pair[0] > pair[1]);
+
+        match (increasing, decreasing) {
+            (true, _) => Ok((ts[0], ts[8])),
+            (_, true) => Ok((ts[8], ts[0])),
+            _ => Err(format!(
+                "{} transform is not strictly monotonic over the range",
+                self.name()
+            )),
+        }
+    }
 }
 
-impl std::fmt::Display for BoundTransformType {
+impl Display for BoundTransformType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             BoundTransformType::Linear(_) => write!(f, "None"),
             BoundTransformType::Log(_) => write!(f, "Log"),
+            BoundTransformType::Log10(_) => write!(f, "Log10"),
             BoundTransformType::Logistic(_) => write!(f, "Logistic"),
             BoundTransformType::BiLog(_) => write!(f, "BiLog"),
+            BoundTransformType::Sqrt(_) => write!(f, "Sqrt"),
+            BoundTransformType::Power(power) => write!(f, "Power({})", power.p),
+            BoundTransformType::Reciprocal(_) => write!(f, "Reciprocal"),
+            BoundTransformType::Affine(affine) => {
+                write!(f, "Affine({}, {})", affine.scale, affine.offset)
+            }
+            BoundTransformType::Custom(custom) => write!(f, "Custom({})", custom.name),
         }
     }
 }
@@ -85,7 +228,7 @@ pub struct Log {}
 
 impl BoundTransformTrait for Log {
     fn transform(&self, x: f_) -> f_ {
-        if x <= 0.0 {
+        if !self.valid(x) {
             panic!("Bound cannot be set to <= 0 for bound with log transformation!");
         }
         x.ln()
@@ -94,6 +237,30 @@ impl BoundTransformTrait for Log {
     fn inv_transform(&self, x: f_) -> f_ {
         x.exp()
     }
+
+    fn valid(&self, x: f_) -> bool {
+        x > 0.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Log10 {}
+
+impl BoundTransformTrait for Log10 {
+    fn transform(&self, x: f_) -> f_ {
+        if !self.valid(x) {
+            panic!("Bound cannot be set to <= 0 for bound with log10 transformation!");
+        }
+        x.log10()
+    }
+
+    fn inv_transform(&self, x: f_) -> f_ {
+        (10.0 as f_).powf(x)
+    }
+
+    fn valid(&self, x: f_) -> bool {
+        x > 0.0
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -105,7 +272,7 @@ impl BoundTransformTrait for BiLog {
     }
 
     fn inv_transform(&self, x: f_) -> f_ {
-        x.signum() * (x.abs() - 1.0).exp()
+        x.signum() * (x.abs().exp() - 1.0)
     }
 }
 
@@ -114,7 +281,7 @@ pub struct Logistic {}
 
 impl BoundTransformTrait for Logistic {
     fn transform(&self, x: f_) -> f_ {
-        if x >= 1.0 || x <= 0.0 {
+        if !self.valid(x) {
             panic!("Bound must be set between 1 and 0 for bound with logistic transformation!");
         }
 
@@ -124,4 +291,222 @@ impl BoundTransformTrait for Logistic {
     fn inv_transform(&self, x: f_) -> f_ {
         1.0 / (1.0 + x.neg().exp())
     }
+
+    fn valid(&self, x: f_) -> bool {
+        x > 0.0 && x < 1.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sqrt {}
+
+impl BoundTransformTrait for Sqrt {
+    fn transform(&self, x: f_) -> f_ {
+        if !self.valid(x) {
+            panic!("Bound cannot be set to < 0 for bound with sqrt transformation!");
+        }
+        x.sqrt()
+    }
+
+    fn inv_transform(&self, x: f_) -> f_ {
+        x.powi(2)
+    }
+
+    fn valid(&self, x: f_) -> bool {
+        x >= 0.0
+    }
+}
+
+/// Power transformation `x^p` of non-negative values, or positive values for `p < 0`.
+#[derive(Debug, Clone)]
+pub struct Power {
+    p: f_,
+}
+
+impl BoundTransformTrait for Power {
+    fn transform(&self, x: f_) -> f_ {
+        if !self.valid(x) {
+            panic!(
+                "Bound cannot be set to < 0 (or <= 0 for negative exponents) for bound with power transformation!"
+            );
+        }
+        x.powf(self.p)
+    }
+
+    fn inv_transform(&self, x: f_) -> f_ {
+        x.powf(self.p.recip())
+    }
+
+    fn valid(&self, x: f_) -> bool {
+        match self.p > 0.0 {
+            true => x >= 0.0,
+            false => x > 0.0,
+        }
+    }
+}
+
+/// Decreasing transformation `1/x`, for bounds that do not contain 0.
+#[derive(Debug, Clone)]
+pub struct Reciprocal {}
+
+impl BoundTransformTrait for Reciprocal {
+    fn transform(&self, x: f_) -> f_ {
+        if !self.valid(x) {
+            panic!("Bound cannot be set to 0 for bound with reciprocal transformation!");
+        }
+        x.recip()
+    }
+
+    fn inv_transform(&self, x: f_) -> f_ {
+        x.recip()
+    }
+
+    fn valid(&self, x: f_) -> bool {
+        x != 0.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Affine {
+    scale: f_,
+    offset: f_,
+}
+
+impl BoundTransformTrait for Affine {
+    fn transform(&self, x: f_) -> f_ {
+        self.scale * x + self.offset
+    }
+
+    fn inv_transform(&self, x: f_) -> f_ {
+        (x - self.offset) / self.scale
+    }
+}
+
+/// User-defined transform, shared between the copies of the bounds it is used for.
+#[derive(Clone)]
+pub struct Custom {
+    name: String,
+    transform: Arc<dyn BoundTransformTrait + Send + Sync>,
+}
+
+impl Custom {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Debug for Custom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Custom").field("name", &self.name).finish()
+    }
+}
+
+impl BoundTransformTrait for Custom {
+    fn transform(&self, x: f_) -> f_ {
+        self.transform.transform(x)
+    }
+
+    fn inv_transform(&self, x: f_) -> f_ {
+        self.transform.inv_transform(x)
+    }
+
+    fn valid(&self, x: f_) -> bool {
+        self.transform.valid(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Cube {}
+
+    impl BoundTransformTrait for Cube {
+        fn transform(&self, x: f_) -> f_ {
+            x.powi(3)
+        }
+
+        fn inv_transform(&self, x: f_) -> f_ {
+            x.cbrt()
+        }
+    }
+
+    /// Sweeps `transform` over `range`, checking the round trip and the order of the transformed
+    /// range.
+    fn check_round_trip(transform: BoundTransform, range: (f_, f_), decreasing: bool) {
+        let transform = transform.new_transform();
+
+        for i in 0..=100 {
+            let x = range.0 + (range.1 - range.0) * i as f_ / 100.0;
+            let x_rt = transform.inv_transform(transform.transform(x));
+            let tol = 64.0 * f_::EPSILON * x.abs().max(1.0);
+            assert!(
+                (x_rt - x).abs() <= tol,
+                "{} round trip of {} gave {}",
+                transform,
+                x,
+                x_rt
+            );
+        }
+
+        let (lo, hi) = transform.transform_range(range).unwrap();
+        assert!(lo < hi, "{} range is not ordered", transform);
+        let (t_0, t_1) = (transform.transform(range.0), transform.transform(range.1));
+        match decreasing {
+            true => assert_eq!((lo, hi), (t_1, t_0), "{} range is not swapped", transform),
+            false => assert_eq!((lo, hi), (t_0, t_1), "{} range is swapped", transform),
+        }
+    }
+
+    #[test]
+    fn round_trip_log() {
+        check_round_trip(BoundTransform::Log, (1e-3, 1e3), false);
+        check_round_trip(BoundTransform::Log10, (1e-3, 1e3), false);
+    }
+
+    #[test]
+    fn round_trip_logistic() {
+        check_round_trip(BoundTransform::Logistic, (0.01, 0.99), false);
+    }
+
+    #[test]
+    fn round_trip_bilog() {
+        check_round_trip(BoundTransform::BiLog, (-100.0, 100.0), false);
+    }
+
+    #[test]
+    fn round_trip_sqrt() {
+        check_round_trip(BoundTransform::Sqrt, (0.0, 100.0), false);
+    }
+
+    #[test]
+    fn round_trip_power() {
+        check_round_trip(BoundTransform::Power(2.5), (0.0, 10.0), false);
+        check_round_trip(BoundTransform::Power(0.5), (0.0, 10.0), false);
+        check_round_trip(BoundTransform::Power(-2.0), (0.1, 10.0), true);
+    }
+
+    #[test]
+    fn round_trip_reciprocal() {
+        check_round_trip(BoundTransform::Reciprocal, (0.1, 10.0), true);
+        check_round_trip(BoundTransform::Reciprocal, (-10.0, -0.1), true);
+    }
+
+    #[test]
+    fn round_trip_affine() {
+        let affine = |scale, offset| BoundTransform::Affine { scale, offset };
+        check_round_trip(affine(2.0, -1.0), (-5.0, 5.0), false);
+        check_round_trip(affine(-0.5, 3.0), (-5.0, 5.0), true);
+    }
+
+    #[test]
+    fn round_trip_custom() {
+        check_round_trip(BoundTransform::custom("cube", Cube {}), (-2.0, 2.0), false);
+    }
+
+    #[test]
+    fn transform_range_outside_domain() {
+        let log = BoundTransform::Log.new_transform();
+        assert!(log.transform_range((-1.0, 1.0)).is_err());
+    }
 }
@@ -5,11 +5,14 @@ use ndarray::*;
 use ndarray_linalg::{error::LinalgError, *};
 
 use crate::acq::ExpectedImprovement;
+use crate::bounds::Fidelity;
 use crate::bounds_array::ArrayBounds;
 use crate::f_;
 use crate::hyp_opt::HyperparameterOptimizer;
 use crate::kernel::{Kernel, KernelState};
-use crate::memory::{Memory, MemoryState};
+use crate::memory::{ForgetStrategy, Memory, MemoryState, OutlierClipping};
+use crate::output_warping::OutputWarping;
+use crate::priors::{HyperPriors, Prior};
 use crate::utils::{Array1Utils, Array2Utils};
 use statrs::distribution::Normal;
 
@@ -19,7 +22,7 @@ pub struct GP<kern: Kernel> {
     pub beta: f_,
     pub search_dom: ArrayBounds,
     pub kernel: kern,
-    pub prior_sigma: f_,
+    pub priors: HyperPriors,
     // pub state: GPState,
     pub mem: Memory,
     pub K: Array2<f_>,
@@ -27,13 +30,15 @@ pub struct GP<kern: Kernel> {
     pub L: CholeskyFactorized<OwnedRepr<f_>>,
     pub alpha: Array2<f_>,
     pub n: Normal,
+    pub fidelity_cov: Option<Array2<f_>>, // indexed by levels below the highest fidelity
+    pub categorical: Vec<usize>,
 }
 
 impl<kern: Kernel> GP<kern>
 where
     Self: HyperparameterOptimizer + ExpectedImprovement,
 {
-    pub fn new(bounds: ArrayBounds, beta: f_, prior_sigma: f_) -> GP<kern> {
+    pub fn new(bounds: ArrayBounds, beta: f_, priors: HyperPriors) -> GP<kern> {
         let dim = bounds.dim();
         let search_dom = ArrayBounds::new_continuous(dim, beta, -beta);
         // let search_dom_LHS = search_dom.LHS_sample(5);
@@ -43,7 +48,7 @@ where
             beta,
             search_dom,
             // search_dom_LHS,
-            prior_sigma,
+            priors,
             kernel: kern::new(dim),
             mem: Memory::new(dim),
             K: Array2::eye(dim),
@@ -53,9 +58,146 @@ where
                 .expect("Should never fail during init."),
             alpha: Array2::ones((dim, 1)),
             n: Normal::new(0.0, 1.0).unwrap(),
+            fidelity_cov: None,
+            categorical: vec![],
         }
     }
 
+    /// Models the dimensions at `dims` as categorical, with the categories given by the integer
+    /// parts of the coordinates.
+    pub fn with_categorical(mut self, dims: Vec<usize>) -> GP<kern> {
+        self.kernel.set_categorical(dims.clone());
+        self.mem.set_categorical(dims.clone());
+        self.categorical = dims;
+        self
+    }
+
+    /// Warps the outputs before they are modelled, see [`OutputWarping`].
+    pub fn with_output_warping(mut self, warping: OutputWarping) -> GP<kern> {
+        self.mem.set_output_warping(warping);
+        self
+    }
+
+    /// Clips outlying outputs before they are modelled, see [`OutlierClipping`].
+    pub fn with_outlier_clipping(mut self, clipping: OutlierClipping) -> GP<kern> {
+        self.mem.set_outlier_clipping(clipping);
+        self
+    }
+
+    /// Order in which observations outside the search domain are forgotten, see [`ForgetStrategy`].
+    pub fn with_forget_strategy(mut self, strategy: ForgetStrategy) -> GP<kern> {
+        self.mem.set_forget_strategy(strategy);
+        self
+    }
+
+    /// Keeps the forgotten observations in an archive of the memory.
+    pub fn with_archive(mut self, archive: bool) -> GP<kern> {
+        self.mem.set_archive(archive);
+        self
+    }
+
+    /// Enables the autoregressive multi-fidelity model if the bounds have a fidelity parameter.
+    /// This is a fixed-correlation approximation: `rho` and `nu` of the fidelity are used as given
+    /// and are not learned with the hyperparameters of the kernel.
+    pub fn with_fidelity(mut self, fidelity: Option<&Fidelity>) -> GP<kern> {
+        self.fidelity_cov = fidelity.map(|fidelity| {
+            let top = fidelity.top();
+
+            let mut var = vec![1.0];
+            for t in 1..fidelity.n() {
+                var.push(fidelity.rho().powi(2) * var[t - 1] + fidelity.nu());
+            }
+
+            // Covariance between the fidelities at levels top - i and top - j, normalised to the highest fidelity
+            Array2::from_shape_fn((fidelity.n(), fidelity.n()), |(i, j)| {
+                let (s, t) = (top - i.max(j), top - i.min(j));
+                fidelity.rho().powi((t - s) as i32) * var[s] / var[top]
+            })
+        });
+        self
+    }
+
+    /// Drops input dimension `dim` of the model, projecting the observations onto the remaining dimensions.
+    pub fn remove_dim(&mut self, dim: usize) {
+        let keep: Vec<usize> = (0..self.dim).filter(|i| *i != dim).collect();
+        let thetas: Array1<f_> = self
+            .kernel
+            .thetas()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != dim + 2)
+            .map(|(_, theta)| *theta)
+            .collect();
+
+        self.categorical = self
+            .categorical
+            .iter()
+            .filter(|d| **d != dim)
+            .map(|d| if *d > dim { d - 1 } else { *d })
+            .collect();
+        self.resize(self.bounds.select_dims(&keep), thetas);
+        self.mem.remove_dim(dim);
+        self.priors.remove_dim(dim);
+    }
+
+    /// Inserts input dimension `dim` of the new `bounds` into the model, with every observation at
+    /// `value` and the prior `l_prior` over its length scale.
+    pub fn insert_dim(
+        &mut self,
+        dim: usize,
+        bounds: ArrayBounds,
+        value: f_,
+        categorical: bool,
+        l_prior: Prior,
+    ) {
+        let mut thetas = self.kernel.thetas().to_vec();
+        thetas.insert(dim + 2, 1.0);
+
+        // Same scale relative to the bounds as the average of the other continuous dimensions
+        let cont = self.mem.continuous_dims();
+        let rel_scale = (self.mem.X_scale().diag().select(Axis(0), &cont)
+            / self.bounds.axes_len().select(Axis(0), &cont))
+        .mean();
+        let scale = rel_scale.unwrap_or(1.0) * bounds.axes_len()[dim];
+
+        self.categorical
+            .iter_mut()
+            .filter(|d| **d >= dim)
+            .for_each(|d| *d += 1);
+        if categorical {
+            self.categorical.push(dim);
+        }
+        self.resize(bounds, Array1::from_vec(thetas));
+        self.mem.insert_dim(dim, value, scale, categorical);
+        self.priors.insert_dim(dim, l_prior);
+    }
+
+    fn resize(&mut self, bounds: ArrayBounds, thetas: Array1<f_>) {
+        self.dim = bounds.dim();
+        self.bounds = bounds;
+        self.search_dom = ArrayBounds::new_continuous(self.dim, self.beta, -self.beta);
+        self.kernel = kern::new(self.dim);
+        self.kernel.set_categorical(self.categorical.clone());
+        self.kernel.update_thetas(&thetas);
+    }
+
+    #[inline(always)]
+    fn fidelity_corr(&self, gap_1: usize, gap_2: usize) -> f_ {
+        match &self.fidelity_cov {
+            Some(cov) => cov[(gap_1, gap_2)],
+            None => 1.0,
+        }
+    }
+
+    /// Cross-covariance weights between the observations in memory and a point at `gap` levels below the highest fidelity.
+    fn fidelity_weights(&self, gap: usize) -> Array1<f_> {
+        self.mem
+            .fidelity_gap()
+            .iter()
+            .map(|obs_gap| self.fidelity_corr(*obs_gap, gap))
+            .collect()
+    }
+
     pub fn fit(&mut self) -> Result<(), LinalgError> {
         match (self.kernel.state(), self.mem.state()) {
             (KernelState::Fitted, MemoryState::Fitted) => return Ok(()), // model has already been fitted, early return
@@ -68,6 +210,13 @@ where
             })
             .fill_with_UPLO(UPLO::Lower);
 
+        if let Some(cov) = &self.fidelity_cov {
+            let gaps = self.mem.fidelity_gap();
+            self.K
+                .indexed_iter_mut()
+                .for_each(|((i, j), k)| *k *= cov[(gaps[i], gaps[j])]);
+        }
+
         self.L = self.K.factorizec(UPLO::Lower)?;
         self.Kinv = self.L.invc()?;
 
@@ -92,7 +241,7 @@ where
         }
 
         //test x knownObs
-        let k_diag = self.kernel.k_diag(self.mem.X.view(), x);
+        let k_diag = self.kernel.k_diag(self.mem.X.view(), x) * self.fidelity_weights(0);
 
         let pred_mean = k_diag.dot(&self.alpha.column(0)) + self.mem.y_prime_mean(); //checked
 
@@ -107,6 +256,61 @@ where
         Ok((pred_mean, pred_sigma))
     }
 
+    /// Reduction of the predictive variance of the highest fidelity at `x` from observing `x` at `gap`
+    /// levels below the highest fidelity.
+    pub fn fidelity_variance_reduction(&self, x: ArrayView1<f_>, gap: usize) -> Result<f_> {
+        match (self.kernel.state(), self.mem.state()) {
+            (KernelState::Fitted, MemoryState::Fitted) => (),
+            (_, _) => anyhow::bail!("Cannot predict with unfitted GP Model!"),
+        }
+
+        let k_diag = self.kernel.k_diag(self.mem.X.view(), x);
+        let k_top = &k_diag * &self.fidelity_weights(0);
+        let k_gap = &k_diag * &self.fidelity_weights(gap);
+        let v_gap = self.L.solvec(&k_gap)?;
+
+        let k = self.kernel.k(x, x) - self.kernel.sigma_n().powi(2);
+        let cov = k * self.fidelity_corr(0, gap) - k_top.dot(&v_gap);
+        let var =
+            k * self.fidelity_corr(gap, gap) - k_gap.dot(&v_gap) + self.kernel.sigma_n().powi(2);
+
+        Ok(cov.powi(2) / var.abs())
+    }
+
+    /// Surrogate of the log evaluation cost, sharing the input transform of this model.
+    pub fn cost_model(&self) -> Result<GP<kern>> {
+        let mut gp = GP::new(self.bounds.clone(), self.beta, self.priors.clone())
+            .with_categorical(self.categorical.clone());
+        gp.mem = self.mem.cost_memory();
+
+        gp.fit()?;
+        if gp.optimize_thetas().is_err() {
+            gp.kernel.whiten_l();
+        }
+        gp.fit()?;
+
+        Ok(gp)
+    }
+
+    /// Copy of the model with the hyperparameters `thetas`, fitted to the same observations.
+    pub fn with_thetas(&self, thetas: &Array1<f_>) -> Result<GP<kern>> {
+        let mut gp = GP::new(self.bounds.clone(), self.beta, self.priors.clone())
+            .with_categorical(self.categorical.clone());
+        gp.mem = self.mem.clone();
+        gp.fidelity_cov = self.fidelity_cov.clone();
+        gp.kernel.update_thetas(thetas);
+
+        gp.fit()?;
+
+        Ok(gp)
+    }
+
+    /// Predicted evaluation cost at `x`, in local coordinates.
+    pub fn predict_cost(&self, x: ArrayView1<f_>) -> Result<f_> {
+        let (mean, _) = self.predict_single(x)?;
+        Ok(self.mem.y_test(mean).exp())
+    }
+
     pub fn predict(&self, X: Array2<f_>) -> Result<(Array2<f_>, Array2<f_>)> {
         match (self.kernel.state(), self.mem.state()) {
             (KernelState::Fitted, MemoryState::Fitted) => (),
@@ -114,8 +318,9 @@ where
         }
 
         //test x knownObs
+        let weights = self.fidelity_weights(0);
         let k_diag = Array2::from_shape_fn((X.ncols(), self.mem.X.ncols()), |(i, j)| {
-            self.kernel.k(X.column(i), self.mem.X.column(j))
+            self.kernel.k(X.column(i), self.mem.X.column(j)) * weights[j]
         });
 
         let pred_mean = k_diag.dot(&self.alpha) + self.mem.y_prime_mean(); //checked
@@ -1,5 +1,8 @@
+use std::collections::VecDeque;
+
 use ndarray::{Array1, Array2, ArrayView1, parallel::prelude::*, s};
-use ndarray_linalg::{EigValsh, InverseC, UPLO, error::LinalgError};
+use ndarray_linalg::{EigValsh, InverseC, Norm, UPLO, error::LinalgError};
+use rand::Rng;
 
 use crate::{
     f_,
@@ -8,6 +11,41 @@ use crate::{
     utils::{Array2Utils, Array3Utils, Array4Utils, ArrayBaseUtils, ArrayView2Utils},
 };
 
+/// Optimiser of the hyperparameters of the GP in every step. Both optimise the log length scales,
+/// with `sigma_f` set from the spread of the outputs and `sigma_n` fixed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ThetaOptimizer {
+    /// Single Newton step with backtracking, falling back to gradient steps.
+    #[default]
+    Newton,
+    /// L-BFGS from the previous length scales and `restarts` draws from the priors over the
+    /// length scales, each for at most `max_iter` iterations or until the relative change of the
+    /// log likelihood with prior drops below `tol`.
+    Lbfgs {
+        restarts: usize,
+        max_iter: usize,
+        tol: f_,
+    },
+}
+
+/// Settings of the slice sampler that draws the thetas from their posterior for fully Bayesian
+/// hyperparameters. After `burn_in` sweeps, every `thin`-th sweep is kept until there are
+/// `n_samples` samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThetaSampling {
+    pub n_samples: usize,
+    pub burn_in: usize,
+    pub thin: usize,
+}
+
+/// Number of past steps from which L-BFGS approximates the inverse Hessian.
+const LBFGS_MEMORY: usize = 5;
+
+/// Initial width of the slice along a log theta, and the maximum number of steps by which the
+/// slice is stepped out or shrunk.
+const SLICE_WIDTH: f_ = 1.0;
+const SLICE_STEPS: usize = 20;
+
 pub trait HyperparameterOptimizer {
     fn log_lik(&self) -> f_;
 
@@ -15,10 +53,28 @@ pub trait HyperparameterOptimizer {
 
     fn log_lik_jac(&self) -> Array1<f_>;
 
+    /// Gradient and diagonal Hessian of the log prior over `sigma_f` and the length scales, in the
+    /// order of [`HyperparameterOptimizer::log_lik_jac`].
+    fn log_prior_derivs(&self) -> (Array1<f_>, Array1<f_>);
+
     fn log_lik_hess(&self) -> Array2<f_>;
 
     fn optimize_thetas(&mut self) -> Result<(), LinalgError>;
 
+    /// Optimises the thetas with multi-start L-BFGS, see [`ThetaOptimizer::Lbfgs`], and returns the
+    /// final log marginal likelihood.
+    fn optimize_thetas_lbfgs(
+        &mut self,
+        restarts: usize,
+        max_iter: usize,
+        tol: f_,
+    ) -> Result<f_, LinalgError>;
+
+    /// Draws thetas from their posterior with slice sampling, starting from the current thetas,
+    /// which are restored afterwards. Only the length scales are sampled, `sigma_f` and `sigma_n`
+    /// are held fixed.
+    fn sample_thetas(&mut self, sampling: &ThetaSampling) -> Result<Vec<Array1<f_>>, LinalgError>;
+
     fn backtrack(
         &mut self,
         base_log_lik: f_,
@@ -42,9 +98,7 @@ impl<kern: Kernel> HyperparameterOptimizer for GP<kern> {
     }
 
     fn log_lik_with_prior(&self, _base_thetas: ArrayView1<f_>) -> f_ {
-        self.log_lik()
-        //Prior over ln length scales
-        -0.5 * self.prior_sigma.powi(2).recip() * self.kernel.ln_l().dot(&self.kernel.ln_l())
+        self.log_lik() + self.priors.ln_pdf(self.kernel.ln_l().view())
     }
 
     //checked
@@ -58,18 +112,25 @@ impl<kern: Kernel> HyperparameterOptimizer for GP<kern> {
 
         let inner = &self.alpha.dot(&self.alpha.t()) - &self.Kinv;
 
-        let mut grad: Array1<f_> = self
+        let grad: Array1<f_> = self
             .kernel
             .calc_thetas_jac(&self.K, &self.mem)
             .outer_iter()
             .map(|jac| 0.5 * jac.product_trace(&inner.view()))
             .collect();
 
-        grad.indexed_iter_mut()
-            .skip(1)
-            .for_each(|(i, val)| *val -= self.kernel.ln_l()[i - 1] / self.prior_sigma.powi(2));
+        grad + self.log_prior_derivs().0
+    }
+
+    fn log_prior_derivs(&self) -> (Array1<f_>, Array1<f_>) {
+        let ln_l = self.kernel.ln_l();
+        // Only the length scales take priors, so the derivatives along sigma_f are zero
+        let with_sigma_f = |derivs: Array1<f_>| std::iter::once(0.0).chain(derivs).collect();
 
-        grad
+        (
+            with_sigma_f(self.priors.ln_pdf_grad(ln_l.view())),
+            with_sigma_f(self.priors.ln_pdf_hess(ln_l.view())),
+        )
     }
 
     // Calcs -H so that invc can be used, checked
@@ -106,10 +167,8 @@ impl<kern: Kernel> HyperparameterOptimizer for GP<kern> {
 
         // let mut hess = Array2::from_shape_fn((self.dim + 1, self.dim + 1), hess_fill_fn);
 
-        let prior_sigma = self.prior_sigma;
-        hess.slice_mut(s![1.., 1..])
-            .diag_mut()
-            .par_mapv_inplace(|val| val + 1.0 / prior_sigma.powi(2));
+        hess.diag_mut()
+            .zip_mut_with(&self.log_prior_derivs().1, |val, prior| *val -= prior);
 
         hess
         // hess.fill_with_UPLO(UPLO::Upper)
@@ -122,17 +181,8 @@ impl<kern: Kernel> HyperparameterOptimizer for GP<kern> {
         //     ((1.0 / self.mem.n() as f64) * (self.mem.y_m().dot(&self.alpha))[0]).sqrt().ln();
 
         // println!("princp {}", new_sigma_f);
-        //Moore et. al.
-
-        let mut new_sigma_f = (2.0 * self.mem.y_prime_std_dev()).ln();
-        if new_sigma_f.is_infinite() {
-            //if std_dev is zero
-            new_sigma_f = (0.01 as f_).ln();
-        }
 
-        // println!("stddev {}", new_sigma_f);
-
-        thetas[0] = new_sigma_f;
+        thetas[0] = self.ln_sigma_f_estimate();
         self.kernel.update_thetas(&thetas.clone().exp());
         self.fit()?;
 
@@ -177,6 +227,72 @@ impl<kern: Kernel> HyperparameterOptimizer for GP<kern> {
         }
     }
 
+    fn optimize_thetas_lbfgs(
+        &mut self,
+        restarts: usize,
+        max_iter: usize,
+        tol: f_,
+    ) -> Result<f_, LinalgError> {
+        let mut thetas = self.kernel.thetas().to_owned();
+        thetas[0] = self.ln_sigma_f_estimate().exp();
+        self.kernel.update_thetas(&thetas);
+
+        // Length scales with a flat prior are restarted from random perturbations instead
+        let start = self.kernel.ln_l();
+        let mut starts = vec![start.clone()];
+        for _ in 0..restarts {
+            starts.push(
+                start
+                    .iter()
+                    .zip(self.priors.l.iter())
+                    .map(|(ln_l, prior)| {
+                        prior
+                            .sample()
+                            .unwrap_or_else(|| ln_l + rand::rng().random_range(-1.0..1.0))
+                    })
+                    .collect(),
+            );
+        }
+
+        let best = starts
+            .into_iter()
+            .filter_map(|ln_l| self.lbfgs(ln_l, max_iter, tol))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(ln_l, _)| ln_l)
+            .unwrap_or(start);
+
+        thetas.slice_mut(s![2..]).assign(&best.exp());
+        self.kernel.update_thetas(&thetas);
+        self.fit()?;
+
+        Ok(self.log_lik())
+    }
+
+    fn sample_thetas(&mut self, sampling: &ThetaSampling) -> Result<Vec<Array1<f_>>, LinalgError> {
+        let start = self.kernel.thetas().to_owned();
+        let mut ln_thetas = start.mapv(f_::ln);
+        let mut log_post = self.log_posterior(&ln_thetas);
+        let dims: Vec<usize> = (2..start.len()).collect();
+
+        let mut samples = vec![];
+        for sweep in 0..sampling.burn_in + sampling.n_samples * sampling.thin {
+            for d in dims.iter() {
+                (ln_thetas, log_post) = self.slice_step(ln_thetas, log_post, *d);
+            }
+
+            if sweep >= sampling.burn_in
+                && (sweep - sampling.burn_in + 1).is_multiple_of(sampling.thin)
+            {
+                samples.push(ln_thetas.mapv(f_::exp));
+            }
+        }
+
+        self.kernel.update_thetas(&start);
+        self.fit()?;
+
+        Ok(samples)
+    }
+
     fn backtrack(
         &mut self,
         base_log_lik: f_,
@@ -191,7 +307,7 @@ impl<kern: Kernel> HyperparameterOptimizer for GP<kern> {
                 .slice_mut(s![2..])
                 .iter_mut()
                 .zip(delta.iter())
-                .for_each(|(theta, delta)| *theta = backtrack_base.powi(i) as f_ * delta); //check sign
+                .for_each(|(theta, delta)| *theta += backtrack_base.powi(i) as f_ * delta); //check sign
 
             self.kernel.update_thetas(&thetas.to_owned().exp());
             self.fit()?;
@@ -205,3 +321,171 @@ impl<kern: Kernel> HyperparameterOptimizer for GP<kern> {
         Ok(())
     }
 }
+
+impl<kern: Kernel> GP<kern> {
+    /// Log of `sigma_f` from the spread of the outputs, Moore et al.
+    fn ln_sigma_f_estimate(&self) -> f_ {
+        match (2.0 * self.mem.y_prime_std_dev()).ln() {
+            // if std_dev is zero
+            ln_sigma_f if ln_sigma_f.is_infinite() => (0.01 as f_).ln(),
+            ln_sigma_f => ln_sigma_f,
+        }
+    }
+
+    /// Fits the model with the log length scales `ln_l` and returns the log likelihood with prior,
+    /// or `None` if the model cannot be fitted.
+    fn fit_ln_l(&mut self, ln_l: &Array1<f_>) -> Option<f_> {
+        let mut thetas = self.kernel.thetas().to_owned();
+        thetas.slice_mut(s![2..]).assign(&ln_l.mapv(f_::exp));
+        self.kernel.update_thetas(&thetas);
+        self.fit().ok()?;
+
+        let log_lik = self.log_lik_with_prior(thetas.view());
+        log_lik.is_finite().then_some(log_lik)
+    }
+
+    /// Log likelihood with prior at the log thetas `ln_thetas`, or negative infinity if the model
+    /// cannot be fitted.
+    fn log_posterior(&mut self, ln_thetas: &Array1<f_>) -> f_ {
+        self.kernel.update_thetas(&ln_thetas.mapv(f_::exp));
+
+        match self.fit() {
+            Ok(_) => match self.log_lik_with_prior(ln_thetas.view()) {
+                log_post if log_post.is_nan() => f_::NEG_INFINITY,
+                log_post => log_post,
+            },
+            Err(_) => f_::NEG_INFINITY,
+        }
+    }
+
+    /// Univariate slice sampling step along log theta `d` with stepping out and shrinkage, Neal
+    /// (2003). Returns the new log thetas and their log likelihood with prior.
+    fn slice_step(
+        &mut self,
+        mut ln_thetas: Array1<f_>,
+        log_post: f_,
+        d: usize,
+    ) -> (Array1<f_>, f_) {
+        let rng = &mut rand::rng();
+        let level = log_post + rng.random::<f_>().ln();
+        let start = ln_thetas[d];
+
+        let mut lower = start - SLICE_WIDTH * rng.random::<f_>();
+        let mut upper = lower + SLICE_WIDTH;
+        for _ in 0..SLICE_STEPS {
+            ln_thetas[d] = lower;
+            if self.log_posterior(&ln_thetas) <= level {
+                break;
+            }
+            lower -= SLICE_WIDTH;
+        }
+        for _ in 0..SLICE_STEPS {
+            ln_thetas[d] = upper;
+            if self.log_posterior(&ln_thetas) <= level {
+                break;
+            }
+            upper += SLICE_WIDTH;
+        }
+
+        for _ in 0..SLICE_STEPS {
+            ln_thetas[d] = lower + rng.random::<f_>() * (upper - lower);
+
+            let new_log_post = self.log_posterior(&ln_thetas);
+            if new_log_post > level {
+                return (ln_thetas, new_log_post);
+            }
+
+            match ln_thetas[d] < start {
+                true => lower = ln_thetas[d],
+                false => upper = ln_thetas[d],
+            }
+        }
+
+        ln_thetas[d] = start;
+        (ln_thetas, log_post)
+    }
+
+    /// Maximises the log likelihood with prior over the log length scales with L-BFGS from
+    /// `ln_l`, returning the final log length scales and log likelihood with prior.
+    fn lbfgs(
+        &mut self,
+        mut ln_l: Array1<f_>,
+        max_iter: usize,
+        tol: f_,
+    ) -> Option<(Array1<f_>, f_)> {
+        let mut log_lik = self.fit_ln_l(&ln_l)?;
+        let mut grad = self.log_lik_jac().slice_move(s![1..]);
+        let mut history = VecDeque::new();
+
+        for _ in 0..max_iter {
+            // Steps change every length scale by at most a factor of e^2
+            let mut dir = lbfgs_direction(&grad, &history);
+            let max_step = dir.iter().fold(0.0 as f_, |max, val| max.max(val.abs()));
+            if max_step > 2.0 {
+                dir /= max_step / 2.0;
+            }
+
+            let slope = grad.dot(&dir);
+            if slope.is_nan() || slope <= 0.0 {
+                break;
+            }
+
+            // Backtracking line search with the Armijo condition
+            let step = (0..20).map(|i| (0.5 as f_).powi(i)).find_map(|t| {
+                let new_ln_l = &ln_l + &(t * &dir);
+                self.fit_ln_l(&new_ln_l)
+                    .filter(|new_log_lik| *new_log_lik >= log_lik + 1e-4 * t * slope)
+                    .map(|new_log_lik| (new_ln_l, new_log_lik))
+            });
+            let Some((new_ln_l, new_log_lik)) = step else {
+                break;
+            };
+
+            let new_grad = self.log_lik_jac().slice_move(s![1..]);
+            let s = &new_ln_l - &ln_l;
+            let y = &grad - &new_grad;
+            if s.dot(&y) > f_::EPSILON {
+                history.push_back((s, y));
+                if history.len() > LBFGS_MEMORY {
+                    history.pop_front();
+                }
+            }
+
+            let converged = (new_log_lik - log_lik).abs() <= tol * log_lik.abs().max(1.0);
+            (ln_l, log_lik, grad) = (new_ln_l, new_log_lik, new_grad);
+
+            if converged {
+                break;
+            }
+        }
+
+        Some((ln_l, log_lik))
+    }
+}
+
+/// Ascent direction of L-BFGS at the gradient `grad`, from the past steps and the changes of the
+/// gradient of the negative objective, with the two-loop recursion.
+fn lbfgs_direction(grad: &Array1<f_>, history: &VecDeque<(Array1<f_>, Array1<f_>)>) -> Array1<f_> {
+    let mut q = grad.clone();
+    let mut alphas = vec![];
+    for (s, y) in history.iter().rev() {
+        let rho = s.dot(y).recip();
+        let alpha = rho * s.dot(&q);
+        q.scaled_add(-alpha, y);
+        alphas.push((alpha, rho));
+    }
+
+    // Initial inverse Hessian, with a unit first step
+    let gamma = match history.back() {
+        Some((s, y)) => s.dot(y) / y.dot(y),
+        None => grad.norm_l2().max(1.0).recip(),
+    };
+
+    let mut r = q * gamma;
+    for ((s, y), (alpha, rho)) in history.iter().zip(alphas.iter().rev()) {
+        let beta = rho * y.dot(&r);
+        r.scaled_add(alpha - beta, s);
+    }
+
+    r
+}
@@ -6,6 +6,12 @@ use crate::f_;
 use crate::memory::Memory;
 use crate::utils::Array2Utils;
 
+// Noise level used to regularise the kernel matrix, larger for single precision to keep the Cholesky decomposition stable
+#[cfg(feature = "f64")]
+const SIGMA_N: f_ = 1e-6;
+#[cfg(not(feature = "f64"))]
+const SIGMA_N: f_ = 1e-3;
+
 #[derive(Clone, Debug)]
 pub enum KernelState {
     Fitted,
@@ -24,6 +30,7 @@ pub trait Kernel {
     fn thetas(&self) -> &Array1<f_>;
     fn update_thetas(&mut self, new_thetas: &Array1<f_>);
     fn whiten_l(&mut self);
+    fn set_categorical(&mut self, dims: Vec<usize>);
     fn l(&self) -> ArrayView1<f_>;
     fn ln_l(&self) -> Array1<f_>;
     fn sigma_f(&self) -> &f_;
@@ -46,12 +53,25 @@ pub struct SquaredExponential {
     jac_state: DerivState,
     hess: Array4<f_>,
     hess_state: DerivState,
+    categorical: Vec<usize>, // dimensions compared by overlap instead of distance
 }
 
 impl SquaredExponential
 where
     Self: Kernel,
 {
+    /// Distance along dimension `d`, or the overlap distance for categorical dimensions.
+    #[inline(always)]
+    fn dist(&self, a: f_, b: f_, d: usize) -> f_ {
+        match self.categorical.contains(&d) {
+            true => match a.floor() == b.floor() {
+                true => 0.0,
+                false => 1.0,
+            },
+            false => a - b,
+        }
+    }
+
     fn update_l_inv(&mut self) {
         let l = self.l().into_owned();
 
@@ -66,7 +86,7 @@ where
 impl Kernel for SquaredExponential {
     fn new(d: usize) -> Self {
         let mut thetas = Array1::ones((d + 2,));
-        thetas[1] = 1e-6; //set sigma_n;
+        thetas[1] = SIGMA_N; //set sigma_n;
 
         SquaredExponential {
             thetas,
@@ -76,6 +96,7 @@ impl Kernel for SquaredExponential {
             jac_state: DerivState::Uncalculated,
             hess: Array4::zeros((d, d, 0, 0)),
             hess_state: DerivState::Uncalculated,
+            categorical: vec![],
         }
     }
 
@@ -105,14 +126,28 @@ impl Kernel for SquaredExponential {
         }
     }
 
+    /// Resets the length scales to one, except for those of categorical dimensions, which cannot
+    /// be absorbed by rescaling the inputs.
     fn whiten_l(&mut self) {
-        self.thetas.iter_mut().skip(2).for_each(|l| *l = 1.0);
+        self.thetas
+            .iter_mut()
+            .skip(2)
+            .enumerate()
+            .filter(|(d, _)| !self.categorical.contains(d))
+            .for_each(|(_, l)| *l = 1.0);
         self.update_l_inv();
         self.jac_state = DerivState::Uncalculated;
         self.hess_state = DerivState::Uncalculated;
         self.state = KernelState::Unfitted;
     }
 
+    /// Categorical dimensions are modelled with an exponentiated Hamming kernel, where categories
+    /// are the integer parts of the coordinates.
+    fn set_categorical(&mut self, dims: Vec<usize>) {
+        self.categorical = dims;
+        self.state = KernelState::Unfitted;
+    }
+
     fn l(&self) -> ArrayView1<f_> {
         self.thetas.slice(s![2..])
     }
@@ -135,7 +170,7 @@ impl Kernel for SquaredExponential {
             panic!("x1 and x2 should have the same shape!");
         }
 
-        let dif = &x1 - &x2;
+        let dif = Array1::from_shape_fn((x1.len(),), |d| self.dist(x1[d], x2[d], d));
 
         let exponent = -0.5 * (dif.dot(&self.l_inv).dot(&dif));
 
@@ -182,7 +217,7 @@ impl Kernel for SquaredExponential {
             .for_each(|(d, mut K)| {
                 K.indexed_iter_mut().for_each(|((i, j), val)| {
                     val.mul_assign(
-                        (mem.X.column(i)[d] - mem.X.column(j)[d]).powi(2) / self.l()[d].powi(2),
+                        self.dist(mem.X[(d, i)], mem.X[(d, j)], d).powi(2) / self.l()[d].powi(2),
                     )
                 })
             });
@@ -233,7 +268,7 @@ impl Kernel for SquaredExponential {
                 hess.slice_mut(s![d1 + 1, d2 + 1, .., ..])
                     .indexed_iter_mut()
                     .for_each(|((i, j), val)| {
-                        *val = *val * (mem.X.column(i)[d1] - mem.X.column(j)[d1]).powi(2)
+                        *val = *val * self.dist(mem.X[(d1, i)], mem.X[(d1, j)], d1).powi(2)
                             / self.l()[d1].powi(2)
                     })
             }
@@ -244,7 +279,7 @@ impl Kernel for SquaredExponential {
             hess.slice_mut(s![d + 1, d + 1, .., ..])
                 .indexed_iter_mut()
                 .for_each(|((i, j), val)| {
-                    *val = *val * (mem.X.column(i)[d] - mem.X.column(j)[d]).powi(2)
+                    *val = *val * self.dist(mem.X[(d, i)], mem.X[(d, j)], d).powi(2)
                         / self.l()[d].powi(2)
                         - 2.0 * *val
                 })
@@ -262,7 +297,12 @@ impl Kernel for SquaredExponential {
     fn obs_jac(&self, X: &Array2<f_>, x_test: ArrayView1<f_>) -> Array2<f_> {
         let k_diag = self.k_diag(X.view(), x_test.view());
 
-        let X = X.to_owned().sub_column_view(&x_test);
+        let mut X = X.to_owned().sub_column_view(&x_test);
+
+        // not differentiable along categorical dimensions
+        self.categorical
+            .iter()
+            .for_each(|d| X.row_mut(*d).fill(0.0));
 
         self.l_inv.dot(&X).mul_row(&k_diag)
     }
@@ -3,13 +3,14 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use std::fmt::Display;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use acq::ExpectedImprovement;
-use hyp_opt::HyperparameterOptimizer;
+use acq::{Acquisition, ExpectedImprovement};
+use hyp_opt::{HyperparameterOptimizer, ThetaOptimizer, ThetaSampling};
 // use fallible_option::Fallible::{self, Fail, Success};
 use ndarray::Slice;
 use ndarray::prelude::*;
@@ -21,19 +22,32 @@ pub mod acq;
 pub mod bound_types;
 pub mod bounds;
 pub mod bounds_array;
+pub mod bounds_io;
 pub mod bounds_transforms;
 pub mod gp;
 pub mod hyp_opt;
 pub mod kernel;
 pub mod memory;
+pub mod output_warping;
+pub mod priors;
+pub mod recorder;
+pub mod search_space;
+pub mod trust_region;
 pub mod utils;
 
 #[cfg(feature = "python")]
 pub mod python;
 
-use bounds::{Bounds, Ready};
+use bounds::{BoundReprs, BoundTrait, BoundType, Bounds, Ready};
+use bounds_array::InitialDesign;
 use gp::GP;
 use kernel::{Kernel, SquaredExponential};
+use memory::{Archiving, ForgetStrategy, OutlierClipping, PCAWeighting};
+use output_warping::OutputWarping;
+use priors::{HyperPriors, Prior};
+use recorder::{Frame, FrameRegion, GridPrediction, Recorder};
+use search_space::SearchSpace;
+use trust_region::TrustRegion;
 use utils::Array1Utils;
 
 #[cfg(feature = "python")]
@@ -77,6 +91,9 @@ pub enum TermCond {
     TargetValReached,
     MaxItersReached,
     MaxTimeReached,
+    MaxCostReached,
+    TrustRegionCollapsed,
+    Stagnated,
     DidNotConverge,
 }
 
@@ -86,12 +103,20 @@ pub struct OptimizationSummary {
     n_samples: usize,
     min_x: Array1<f_>,
     min_y: f_,
+    total_cost: f_,
+    recording: Option<Recorder>,
 }
 
 #[derive(Debug, Clone)]
 pub struct LABCATConfig {
     beta: f_, // TODO: back to f_?
     prior_sigma: f_,
+    l_prior: Option<Prior>, // replaces the log-normal prior with standard deviation prior_sigma
+    l_priors: Vec<(usize, Prior)>, // priors of individual length scales, by dimension of the bounds
+    rescale_cap: Option<f_>,
+    theta_optimizer: ThetaOptimizer,
+    theta_sampling: Option<ThetaSampling>,
+    log_lik: Option<f_>,
     restarts: bool,
     target_tol: f_,
     target_val: Option<f_>,
@@ -99,6 +124,25 @@ pub struct LABCATConfig {
     max_samples: Option<usize>,
     max_time: Option<(Duration, Instant)>,
     auto_print: Option<usize>,
+    initial_design: InitialDesign,
+    rotation: bool,
+    pca_weighting: PCAWeighting,
+    rotation_smoothing: Option<f_>,
+    min_tr_len: Option<f_>,
+    max_tr_len: Option<f_>,
+    x_tol: Option<Array1<f_>>,
+    max_stagnation: Option<(usize, f_)>,
+    last_improvement: Option<(usize, f_)>,
+    acquisition: Acquisition,
+    output_warping: OutputWarping,
+    outlier_clipping: OutlierClipping,
+    forget_strategy: ForgetStrategy,
+    archive_forgotten: Archiving,
+    total_cost: f_,
+    max_cost: Option<f_>,
+    next_fidelity: Option<usize>,
+    context: Option<Array1<f_>>,
+    fixed: Vec<(usize, f_)>, // fixed dimensions of the bounds and their values, in ascending order
 }
 
 impl Default for LABCATConfig {
@@ -112,6 +156,12 @@ impl LABCATConfig {
         LABCATConfig {
             beta: 0.5,
             prior_sigma: 0.15,
+            l_prior: None,
+            l_priors: vec![],
+            rescale_cap: Some(0.45),
+            theta_optimizer: ThetaOptimizer::default(),
+            theta_sampling: None,
+            log_lik: None,
             restarts: false,
             target_tol: f_::EPSILON,
             target_val: None,
@@ -119,8 +169,110 @@ impl LABCATConfig {
             max_samples: None,
             max_time: None,
             auto_print: None,
+            initial_design: InitialDesign::default(),
+            rotation: cfg!(feature = "PCA"),
+            pca_weighting: PCAWeighting::default(),
+            rotation_smoothing: None,
+            min_tr_len: None,
+            max_tr_len: None,
+            x_tol: None,
+            max_stagnation: None,
+            last_improvement: None,
+            acquisition: Acquisition::default(),
+            output_warping: OutputWarping::default(),
+            outlier_clipping: OutlierClipping::default(),
+            forget_strategy: ForgetStrategy::default(),
+            archive_forgotten: Archiving::Off,
+            total_cost: 0.0,
+            max_cost: None,
+            next_fidelity: None,
+            context: None,
+            fixed: vec![],
         }
     }
+
+    /// Prior over the length scale of dimension `dim` of the bounds.
+    fn l_prior(&self, dim: usize) -> Prior {
+        match self.l_priors.iter().find(|(d, _)| *d == dim) {
+            Some((_, prior)) => prior.clone(),
+            None => self.l_prior.clone().unwrap_or(Prior::LogNormal {
+                mu: 0.0,
+                sigma: self.prior_sigma,
+            }),
+        }
+    }
+
+    /// Priors over the length scales of a GP over the dimensions of `d` dimensional bounds that
+    /// are not fixed.
+    fn hyper_priors(&self, d: usize) -> HyperPriors {
+        HyperPriors {
+            l: (0..d)
+                .filter(|i| !self.fixed.iter().any(|(dim, _)| dim == i))
+                .map(|i| self.l_prior(i))
+                .collect(),
+        }
+    }
+
+    /// Replaces the prior over the length scale of dimension `dim` of the bounds.
+    fn set_l_prior(&mut self, dim: usize, prior: Prior) {
+        prior.validate();
+        self.l_priors.retain(|(d, _)| *d != dim);
+        self.l_priors.push((dim, prior));
+    }
+
+    fn set_rescale_cap(&mut self, cap: Option<f_>) {
+        match cap {
+            Some(cap) if cap <= 0.0 || cap >= 1.0 => {
+                panic!("Rescale cap must be in the interval (0, 1)!")
+            }
+            _ => (),
+        }
+
+        self.rescale_cap = cap;
+    }
+
+    fn set_trust_region_lens(&mut self, min: Option<f_>, max: Option<f_>) {
+        if [min, max]
+            .iter()
+            .flatten()
+            .any(|len| len.is_nan() || *len <= 0.0)
+        {
+            panic!("Side lengths of the trust region must be positive!")
+        }
+
+        if let (Some(min), Some(max)) = (min, max)
+            && min > max
+        {
+            panic!("Minimum side length of the trust region cannot exceed the maximum!")
+        }
+
+        self.min_tr_len = min;
+        self.max_tr_len = max;
+    }
+
+    fn set_theta_optimizer(&mut self, optimizer: ThetaOptimizer) {
+        if let ThetaOptimizer::Lbfgs { max_iter, tol, .. } = optimizer {
+            match (max_iter, tol) {
+                (0, _) => panic!("Maximum number of L-BFGS iterations must be positive!"),
+                (_, tol) if tol <= 0.0 => panic!("Tolerance of L-BFGS must be positive!"),
+                _ => (),
+            }
+        }
+
+        self.theta_optimizer = optimizer;
+    }
+
+    fn set_theta_sampling(&mut self, n_samples: usize, burn_in: usize, thin: usize) {
+        if n_samples == 0 || thin == 0 {
+            panic!("Number of theta samples and thinning must be positive!")
+        }
+
+        self.theta_sampling = Some(ThetaSampling {
+            n_samples,
+            burn_in,
+            thin,
+        });
+    }
 }
 
 impl OptimizationSummary {
@@ -131,6 +283,9 @@ impl OptimizationSummary {
             TermCond::TargetValReached => "Target value reached!".into(),
             TermCond::MaxItersReached => "Maximum sampling iterations reached!".into(),
             TermCond::MaxTimeReached => "Maximum wall-time reached!".into(),
+            TermCond::MaxCostReached => "Maximum evaluation cost reached!".into(),
+            TermCond::TrustRegionCollapsed => "Trust region collapsed!".into(),
+            TermCond::Stagnated => "No improvement in objective function value!".into(),
             TermCond::DidNotConverge => "Did not converge!".into(),
         }
     }
@@ -146,6 +301,15 @@ impl OptimizationSummary {
     pub fn min_y(&self) -> &f_ {
         &self.min_y
     }
+
+    /// Sum of the evaluation costs of all samples, equal to the number of samples if no costs were given.
+    pub fn total_cost(&self) -> &f_ {
+        &self.total_cost
+    }
+
+    pub fn recording(&self) -> Option<&Recorder> {
+        self.recording.as_ref()
+    }
 }
 
 impl Display for OptimizationSummary {
@@ -162,6 +326,49 @@ impl Display for OptimizationSummary {
     }
 }
 
+/// Target function returning the objective function values and evaluation costs of the samples.
+pub type CostTargetFn = fn(&Array2<f_>) -> (Array1<f_>, Array1<f_>);
+
+/// Target function evaluated at the given fidelity level.
+pub type FidelityTargetFn = fn(&Array2<f_>, usize) -> Array1<f_>;
+
+/// Target function of points in the units of the bounds, see [`LABCAT::set_target_fn_typed`].
+#[cfg(not(feature = "python"))]
+type LabelledTargetFn = Arc<dyn Fn(Vec<BoundReprs>) -> Array1<f_> + Send + Sync>;
+
+#[cfg(not(feature = "python"))]
+#[derive(Clone)]
+enum TargetFn {
+    Plain(fn(&Array2<f_>) -> Array1<f_>),
+    WithCost(CostTargetFn),
+    WithFidelity(FidelityTargetFn),
+    Labelled(LabelledTargetFn),
+}
+
+#[cfg(not(feature = "python"))]
+impl TargetFn {
+    /// Evaluates the target function at `X`, with `reprs` converting `X` to the units of the bounds.
+    fn eval<R: FnOnce(&Array2<f_>) -> Vec<BoundReprs>>(
+        &self,
+        X: &Array2<f_>,
+        fidelity: Option<usize>,
+        reprs: R,
+    ) -> (Array1<f_>, Option<Array1<f_>>) {
+        match self {
+            TargetFn::Plain(f) => (f(X), None),
+            TargetFn::Labelled(f) => (f(reprs(X)), None),
+            TargetFn::WithCost(f) => {
+                let (y, c) = f(X);
+                (y, Some(c))
+            }
+            TargetFn::WithFidelity(f) => (
+                f(X, fidelity.expect("Bounds have no fidelity parameter!")),
+                None,
+            ),
+        }
+    }
+}
+
 #[allow(clippy::type_complexity)]
 pub struct LABCAT<LABCATConfigState = Config> {
     gp: GP<SquaredExponential>,
@@ -169,9 +376,10 @@ pub struct LABCAT<LABCATConfigState = Config> {
     bounds: Bounds<Ready>,
     config: LABCATConfig,
     config_state: PhantomData<LABCATConfigState>,
+    recorder: Option<Recorder>,
 
     #[cfg(not(feature = "python"))]
-    target_fn: Option<fn(&Array2<f_>) -> Array1<f_>>,
+    target_fn: Option<TargetFn>,
 
     #[cfg(not(feature = "python"))]
     init_pts_fn: fn(usize) -> usize,
@@ -188,16 +396,23 @@ impl LABCAT {
         let gp = GP::new(
             bounds.bounds_arr().to_owned(),
             1.0 / bounds.dim() as f_,
-            0.1,
-        );
+            HyperPriors::new(
+                bounds.dim(),
+                Prior::LogNormal {
+                    mu: 0.0,
+                    sigma: 0.1,
+                },
+            ),
+        )
+        .with_fidelity(bounds.fidelity())
+        .with_categorical(bounds.categorical_dims());
         let init_pts_fn = |d: usize| 2 * d + 1;
         let forget_fn = |d: usize| 7 * d;
-        #[cfg(feature = "LHS")]
-        let init_points = bounds.bounds_arr().LHS_sample(init_pts_fn(bounds.dim()));
-        #[cfg(not(feature = "LHS"))]
-        let init_points = bounds.bounds_arr().random_sample(init_pts_fn(bounds.dim()));
-        let gp_state = GPState::Init(init_points);
         let config = LABCATConfig::new();
+        let init_points = bounds
+            .bounds_arr()
+            .sample(init_pts_fn(bounds.dim()), &config.initial_design);
+        let gp_state = GPState::Init(init_points);
 
         LABCAT {
             gp,
@@ -208,8 +423,14 @@ impl LABCAT {
             init_pts_fn,
             forget_fn,
             config_state: PhantomData,
+            recorder: None,
         }
     }
+
+    /// Algorithm over the bounds of the search space `T`, see [`LABCAT::set_target_fn_typed`].
+    pub fn new_typed<T: SearchSpace>() -> LABCAT<Config> {
+        LABCAT::new(T::bounds())
+    }
 }
 
 #[cfg(not(feature = "python"))]
@@ -219,11 +440,54 @@ impl LABCAT<Config> {
         self
     }
 
+    /// Standard deviation of the default log-normal prior over the length scales.
     pub fn prior_sigma(mut self, sigma: f_) -> Self {
         self.config.prior_sigma = sigma;
         self
     }
 
+    /// Prior over every length scale, replacing the log-normal prior with standard deviation
+    /// `prior_sigma`. Only the length scales take priors, as `sigma_f` is set from the spread of
+    /// the outputs and `sigma_n` is fixed.
+    pub fn length_scale_prior(mut self, prior: Prior) -> Self {
+        prior.validate();
+        self.config.l_prior = Some(prior);
+        self
+    }
+
+    /// Prior over the length scale of the bound labelled `label`.
+    pub fn length_scale_prior_of(mut self, label: &str, prior: Prior) -> Self {
+        let dim = self
+            .bounds
+            .iter_bounds()
+            .position(|bound| bound.label() == label)
+            .unwrap_or_else(|| panic!("Bound {} could not be found!", label));
+
+        self.config.set_l_prior(dim, prior);
+        self
+    }
+
+    /// Optimiser of the hyperparameters of the GP in every step.
+    pub fn theta_optimizer(mut self, optimizer: ThetaOptimizer) -> Self {
+        self.config.set_theta_optimizer(optimizer);
+        self
+    }
+
+    /// Integrates the expected improvement over `n_samples` draws of the thetas from their
+    /// posterior instead of using only the optimised thetas, e.g. for few observations. The draws
+    /// are slice sampled, keeping every `thin`-th sweep after `burn_in` sweeps.
+    pub fn theta_sampling(mut self, n_samples: usize, burn_in: usize, thin: usize) -> Self {
+        self.config.set_theta_sampling(n_samples, burn_in, thin);
+        self
+    }
+
+    /// Largest relative change of the length scales by which the inputs are rescaled every step,
+    /// or `None` to rescale by the fitted length scales.
+    pub fn rescale_cap(mut self, cap: Option<f_>) -> Self {
+        self.config.set_rescale_cap(cap);
+        self
+    }
+
     pub fn init_pts_fn(mut self, f: fn(usize) -> usize) -> Self {
         self.init_pts_fn = f;
         self
@@ -234,23 +498,126 @@ impl LABCAT<Config> {
         self
     }
 
+    pub fn initial_design(mut self, design: InitialDesign) -> Self {
+        self.config.initial_design = design;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: bool) -> Self {
+        self.config.rotation = rotation;
+        self
+    }
+
+    pub fn pca_weighting(mut self, weighting: PCAWeighting) -> Self {
+        match weighting {
+            PCAWeighting::Softmin(temp) if temp <= 0.0 || !temp.is_finite() => {
+                panic!("Temperature of softmin PCA weighting must be positive!")
+            }
+            PCAWeighting::TopK(0) => {
+                panic!("Number of observations of top-k PCA weighting must be at least 1!")
+            }
+            _ => (),
+        }
+
+        self.config.pca_weighting = weighting;
+        self
+    }
+
+    /// Weight of the newest rotation in an exponential moving average over iterations, in (0, 1].
+    pub fn rotation_smoothing(mut self, alpha: f_) -> Self {
+        if alpha <= 0.0 || alpha > 1.0 {
+            panic!("Rotation smoothing factor must be in the interval (0, 1]!")
+        }
+
+        self.config.rotation_smoothing = Some(alpha);
+        self
+    }
+
+    /// Records the state of the algorithm every time a suggestion is made.
+    pub fn record(mut self, mut recorder: Recorder) -> Self {
+        recorder.set_bounds(self.bounds.clone());
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn acquisition(mut self, acquisition: Acquisition) -> Self {
+        self.config.acquisition = acquisition;
+        self
+    }
+
+    /// Warping of the outputs before they are modelled, e.g. for objectives spanning many orders of magnitude.
+    pub fn output_warping(mut self, warping: OutputWarping) -> Self {
+        if let OutputWarping::Log(offset) = warping
+            && offset <= 0.0
+        {
+            panic!("Offset of log output warping must be positive!")
+        }
+
+        self.config.output_warping = warping;
+        self
+    }
+
+    /// Clipping of outlying large outputs before they are modelled, e.g. from failed evaluations.
+    /// The unclipped outputs are still reported.
+    pub fn outlier_clipping(mut self, clipping: OutlierClipping) -> Self {
+        match clipping {
+            OutlierClipping::Mad(k) if k <= 0.0 => {
+                panic!("Number of deviations of outlier clipping must be positive!")
+            }
+            OutlierClipping::Quantile(q) if q <= 0.5 || q > 1.0 => {
+                panic!("Quantile of outlier clipping must be in the interval (0.5, 1]!")
+            }
+            _ => (),
+        }
+
+        self.config.outlier_clipping = clipping;
+        self
+    }
+
+    /// Order in which observations outside the trust region are forgotten.
+    pub fn forget_strategy(mut self, strategy: ForgetStrategy) -> Self {
+        self.config.forget_strategy = strategy;
+        self
+    }
+
+    /// Keeps the forgotten observations in an archive, which is only reloaded on restart with
+    /// [`Archiving::ReloadOnRestart`].
+    pub fn archive_forgotten(mut self, archiving: Archiving) -> Self {
+        self.config.archive_forgotten = archiving;
+        self
+    }
+
+    /// Minimum side length of the trust region, in the original units of the bounds.
+    pub fn min_trust_region(mut self, len: f_) -> Self {
+        self.config
+            .set_trust_region_lens(Some(len), self.config.max_tr_len);
+        self
+    }
+
+    /// Maximum side length of the trust region, in the original units of the bounds.
+    pub fn max_trust_region(mut self, len: f_) -> Self {
+        self.config
+            .set_trust_region_lens(self.config.min_tr_len, Some(len));
+        self
+    }
+
     pub fn build(self) -> LABCAT<Manual> {
         let gp = GP::new(
             self.bounds.bounds_arr().to_owned(),
             self.config.beta,
-            self.config.prior_sigma,
+            self.config.hyper_priors(self.bounds.dim()),
+        )
+        .with_fidelity(self.bounds.fidelity())
+        .with_categorical(self.bounds.categorical_dims())
+        .with_output_warping(self.config.output_warping.clone())
+        .with_outlier_clipping(self.config.outlier_clipping.clone())
+        .with_forget_strategy(self.config.forget_strategy.clone())
+        .with_archive(self.config.archive_forgotten != Archiving::Off);
+
+        let init_points = self.bounds.bounds_arr().sample(
+            (self.init_pts_fn)(self.bounds.dim()),
+            &self.config.initial_design,
         );
-
-        #[cfg(feature = "LHS")]
-        let init_points = self
-            .bounds
-            .bounds_arr()
-            .LHS_sample((self.init_pts_fn)(self.bounds.dim()));
-        #[cfg(not(feature = "LHS"))]
-        let init_points = self
-            .bounds
-            .bounds_arr()
-            .random_sample((self.init_pts_fn)(self.bounds.dim()));
         let gp_state = GPState::Init(init_points);
 
         LABCAT {
@@ -259,6 +626,7 @@ impl LABCAT<Config> {
             bounds: self.bounds,
             config: self.config,
             config_state: PhantomData,
+            recorder: self.recorder,
 
             target_fn: self.target_fn,
             init_pts_fn: self.init_pts_fn,
@@ -273,7 +641,7 @@ impl<S: LABCATReadyState> LABCAT<S> {
     }
 
     pub fn X_min(&self) -> Array1<f_> {
-        self.gp.mem.X_min()
+        self.expand(self.gp.mem.X_min().view())
     }
 
     pub fn y_min(&self) -> f_ {
@@ -284,6 +652,359 @@ impl<S: LABCATReadyState> LABCAT<S> {
         &self.gp_state
     }
 
+    /// Fidelity level at which the last suggestion should be evaluated, or `None` if the bounds have no fidelity parameter.
+    pub fn suggested_fidelity(&self) -> Option<usize> {
+        let fidelity = self.bounds.fidelity()?;
+        Some(self.config.next_fidelity.unwrap_or(fidelity.top()))
+    }
+
+    /// Fidelity level with the largest reduction of the variance at the highest fidelity at `x` per unit of cost.
+    fn select_fidelity(&self, x: ArrayView1<f_>) -> Option<usize> {
+        let fidelity = self.bounds.fidelity()?;
+
+        (0..fidelity.n())
+            .filter_map(|level| {
+                self.gp
+                    .fidelity_variance_reduction(x, fidelity.top() - level)
+                    .ok()
+                    .map(|red| (level, red / fidelity.costs()[level]))
+            })
+            .filter(|(_, val)| val.is_finite())
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(level, _)| level)
+    }
+
+    /// Replaces the context variables of `x`, in the space of the bounds without the fixed
+    /// parameters, with the current context.
+    fn with_context(&self, x: Array1<f_>) -> Array1<f_> {
+        match &self.config.context {
+            Some(context) => {
+                let mut x = self.expand(x.view());
+                self.bounds
+                    .context_dims()
+                    .iter()
+                    .zip(context.iter())
+                    .for_each(|(d, c)| x[*d] = *c);
+                self.reduce(x.view())
+            }
+            None => x,
+        }
+    }
+
+    /// Suggestion in the space of the bounds for the local coordinates `x`, in the current context
+    /// and snapped to the values that the bounds can take.
+    fn suggestion(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        let x = self.with_context(self.gp.mem.x_test(x));
+        self.bounds.snap(self.expand(x.view()).view())
+    }
+
+    /// Candidates `X`, in local coordinates, moved to the local coordinates of their suggestions,
+    /// without those that have already been evaluated.
+    fn unevaluated(&self, X: Array2<f_>) -> Array2<f_> {
+        let mut res = Array2::zeros((self.gp.dim, 0));
+
+        // Candidates are only free along the dimensions that are not context variables, and are
+        // scored at the values that the bounds can take
+        X.columns()
+            .into_iter()
+            .map(|col| self.suggestion(col))
+            .filter(|x| !self.evaluated(x.view()))
+            .for_each(|x| {
+                res.push_column(self.gp.mem.x_prime(self.reduce(x.view()).view()).view())
+                    .expect("push should never fail")
+            });
+
+        res
+    }
+
+    /// Whether the suggestion `x`, in the space of the bounds, has already been evaluated.
+    fn evaluated(&self, x: ArrayView1<f_>) -> bool {
+        let tol = self.bounds.bounds_arr().axes_len() * 1e-9;
+
+        self.gp.mem.X().columns().into_iter().any(|obs| {
+            let obs = self.bounds.snap(self.expand(obs).view());
+            obs.iter()
+                .zip(x.iter())
+                .zip(tol.iter())
+                .all(|((obs, x), tol)| (obs - x).abs() <= *tol)
+        })
+    }
+
+    /// Inserts the values of the fixed parameters into `x`.
+    fn expand(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        let mut x = x.to_vec();
+        self.config
+            .fixed
+            .iter()
+            .for_each(|(dim, val)| x.insert(*dim, *val));
+        Array1::from_vec(x)
+    }
+
+    /// Removes the fixed parameters from `x`.
+    fn reduce(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        x.iter()
+            .enumerate()
+            .filter(|(i, _)| !self.config.fixed.iter().any(|(dim, _)| dim == i))
+            .map(|(_, val)| *val)
+            .collect()
+    }
+
+    fn expand_X(&self, X: Array2<f_>) -> Array2<f_> {
+        match self.config.fixed.is_empty() {
+            true => X,
+            false => Array2::from_shape_fn((self.bounds.dim(), X.ncols()), |(i, j)| {
+                self.expand(X.column(j))[i]
+            }),
+        }
+    }
+
+    fn reduce_X(&self, X: Array2<f_>) -> Array2<f_> {
+        match self.config.fixed.is_empty() {
+            true => X,
+            false => Array2::from_shape_fn((self.gp.dim, X.ncols()), |(i, j)| {
+                self.reduce(X.column(j))[i]
+            }),
+        }
+    }
+
+    /// Position of the bound labelled `label` among the dimensions that are not fixed, and its
+    /// position in the bounds.
+    fn free_dim(&self, label: &str) -> (usize, usize) {
+        let dim = self
+            .bounds
+            .iter_bounds()
+            .position(|bound| bound.label() == label)
+            .unwrap_or_else(|| panic!("Bound {} could not be found!", label));
+        let n_fixed_below = self.config.fixed.iter().filter(|(d, _)| *d < dim).count();
+
+        (dim - n_fixed_below, dim)
+    }
+
+    fn _fix_parameter(&mut self, label: &str, value: f_) {
+        let (free_dim, dim) = self.free_dim(label);
+
+        if self.config.fixed.iter().any(|(d, _)| *d == dim) {
+            panic!("Parameter {} is already fixed!", label)
+        }
+
+        if self.bounds.context_dims().contains(&dim) {
+            panic!("Context variable {} cannot be fixed!", label)
+        }
+
+        if self.config.fixed.len() + 1 == self.bounds.dim() {
+            panic!("Cannot fix every parameter!")
+        }
+
+        let b = self.bounds.bounds_arr().bounds_arr().row(dim);
+        if value < b[0] || value > b[1] {
+            panic!("Fixed value of {} lies outside its bounds!", label)
+        }
+
+        self.gp.remove_dim(free_dim);
+        if let GPState::Init(init_pts) = &self.gp_state {
+            let keep: Vec<usize> = (0..init_pts.nrows()).filter(|i| *i != free_dim).collect();
+            self.gp_state = GPState::Init(init_pts.select(Axis(0), &keep));
+        }
+
+        let pos = self.config.fixed.partition_point(|(d, _)| *d < dim);
+        self.config.fixed.insert(pos, (dim, value));
+    }
+
+    fn _unfix_parameter(&mut self, label: &str) {
+        let (free_dim, dim) = self.free_dim(label);
+
+        let pos = self
+            .config
+            .fixed
+            .iter()
+            .position(|(d, _)| *d == dim)
+            .unwrap_or_else(|| panic!("Parameter {} is not fixed!", label));
+        let (_, value) = self.config.fixed.remove(pos);
+
+        let free_dims: Vec<usize> = (0..self.bounds.dim())
+            .filter(|i| !self.config.fixed.iter().any(|(d, _)| d == i))
+            .collect();
+        let bounds = self.bounds.bounds_arr().select_dims(&free_dims);
+
+        // Remaining initial points are spread out along the released dimension
+        if let GPState::Init(init_pts) = &self.gp_state {
+            let row = bounds
+                .select_dims(&[free_dim])
+                .random_sample(init_pts.ncols());
+            let init_pts = ndarray::concatenate(
+                Axis(0),
+                &[
+                    init_pts.slice(s![..free_dim, ..]),
+                    row.view(),
+                    init_pts.slice(s![free_dim.., ..]),
+                ],
+            )
+            .expect("concatenate should never fail");
+            self.gp_state = GPState::Init(init_pts);
+        }
+
+        let categorical = self.bounds.categorical_dims().contains(&dim);
+        let l_prior = self.config.l_prior(dim);
+        self.gp
+            .insert_dim(free_dim, bounds, value, categorical, l_prior);
+    }
+
+    pub fn bounds(&self) -> &Bounds<Ready> {
+        &self.bounds
+    }
+
+    /// Points stored as columns of `X`, in the units of the bounds.
+    fn reprs(&self, X: &Array2<f_>) -> Vec<BoundReprs> {
+        X.columns()
+            .into_iter()
+            .map(|x| {
+                self.bounds
+                    .repr(x)
+                    .expect("Suggested point should be inside bounds!")
+            })
+            .collect()
+    }
+
+    /// Points in the units of the bounds, stored as columns.
+    fn parse_reprs(&self, X: Vec<BoundReprs>) -> Array2<f_> {
+        let mut res = Array2::zeros((self.bounds.dim(), 0));
+        X.into_iter().for_each(|x| {
+            res.push_column(self.bounds.parse(x).view())
+                .expect("push should never fail")
+        });
+        res
+    }
+
+    /// Parameters that are currently fixed, with their values.
+    pub fn fixed_parameters(&self) -> Vec<(String, f_)> {
+        self.config
+            .fixed
+            .iter()
+            .map(|(dim, val)| {
+                let label = self.bounds.iter_bounds().as_slice()[*dim].label();
+                (label.into(), *val)
+            })
+            .collect()
+    }
+
+    fn apply_context(&self, mut X: Array2<f_>) -> Array2<f_> {
+        if self.config.context.is_some() {
+            X.columns_mut()
+                .into_iter()
+                .for_each(|mut col| col.assign(&self.with_context(col.to_owned())));
+        }
+        X
+    }
+
+    /// Local coordinates of the best observation, or of the observation with the best predicted
+    /// value once its context variables are replaced by the current context.
+    fn centre(&mut self) -> Result<Array1<f_>> {
+        match self.config.context {
+            Some(_) => {
+                self.gp.fit()?;
+                Ok(self.contextual_best()?.0)
+            }
+            None => Ok(self.gp.mem.X.column(self.gp.mem.min_index()).to_owned()),
+        }
+    }
+
+    /// Forgets observations outside the trust region, ignoring the context variables if a context is set.
+    fn forget(&mut self, min: usize) {
+        let X = match self.config.context {
+            Some(_) => {
+                let mut X = self.gp.mem.X.clone();
+                X.columns_mut().into_iter().for_each(|mut col| {
+                    let x = self.with_context(self.gp.mem.x_test(col.view()));
+                    col.assign(&self.gp.mem.x_prime(x.view()));
+                });
+                X
+            }
+            None => self.gp.mem.X.clone(),
+        };
+
+        self.gp.mem.forget_by(&self.gp.search_dom, min, &X);
+    }
+
+    /// Observation with the best predicted value in the current context, in local coordinates, and
+    /// the predicted value.
+    fn contextual_best(&self) -> Result<(Array1<f_>, f_)> {
+        let mut best: Option<(Array1<f_>, f_)> = None;
+
+        for col in self.gp.mem.X.columns() {
+            let x = self.with_context(self.gp.mem.x_test(col));
+            let x = self.gp.mem.x_prime(x.view());
+            let (mean, _) = self.gp.predict_single(x.view())?;
+
+            if best.as_ref().is_none_or(|(_, best)| mean < *best) {
+                best = Some((x, mean));
+            }
+        }
+
+        best.ok_or_else(|| anyhow::format_err!("No observations in memory!"))
+    }
+
+    /// Current trust region, or `None` while the initial points are still being sampled.
+    ///
+    /// The trust region is flat along the dimensions of fixed parameters, and along categorical
+    /// dimensions at the category of the best observation.
+    pub fn trust_region(&self) -> Option<TrustRegion> {
+        match self.gp_state {
+            GPState::Init(_) => None,
+            GPState::Nominal => {
+                let tr = self
+                    .gp
+                    .mem
+                    .trust_region(&self.gp.search_dom)
+                    .select_dims(&self.gp.mem.continuous_dims());
+
+                let x_min = self.gp.mem.X_min();
+                let mut categorical = self.gp.categorical.clone();
+                categorical.sort();
+                let tr = categorical
+                    .iter()
+                    .fold(tr, |tr, dim| tr.insert_flat_dim(*dim, x_min[*dim]));
+
+                Some(
+                    self.config
+                        .fixed
+                        .iter()
+                        .fold(tr, |tr, (dim, val)| tr.insert_flat_dim(*dim, *val)),
+                )
+            }
+        }
+    }
+
+    /// Minimum and maximum side lengths of the trust region along each of its axes, converted from
+    /// the original units of the bounds to their transformed space at the best observation.
+    fn trust_region_lens(&self) -> (Option<Array1<f_>>, Option<Array1<f_>>) {
+        if self.config.min_tr_len.is_none() && self.config.max_tr_len.is_none() {
+            return (None, None);
+        }
+
+        let rate = self.reduce(
+            self.bounds
+                .untransform_rate(self.expand(self.gp.mem.X_min().view()).view())
+                .view(),
+        );
+        // Length in original units of a unit step along each axis of the trust region
+        let axis_rate = self
+            .gp
+            .mem
+            .X_rotate()
+            .columns()
+            .into_iter()
+            .map(|axis| match (&axis * &rate).mapv(|v| v * v).sum().sqrt() {
+                r if r > 0.0 && r.is_finite() => r,
+                _ => 1.0,
+            })
+            .collect::<Array1<f_>>();
+
+        (
+            self.config.min_tr_len.map(|len| len / &axis_rate),
+            self.config.max_tr_len.map(|len| len / &axis_rate),
+        )
+    }
+
     fn x_fill(&self) -> String {
         "\u{02500}"
             .chars()
@@ -331,10 +1052,75 @@ impl<S: LABCATReadyState> LABCAT<S> {
             "\u{02502} {:^6} \u{02502} {:^14} \u{02502} {:>9.5} \u{02502}",
             self.config.n_samples,
             format!("{:.6e}", self.gp.mem.y_min()),
-            self.gp.mem.X_min()
+            self.X_min()
         )
     }
 
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    fn record_frame(&mut self, suggestion: &Array2<f_>) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+
+        let bounds: Vec<&BoundType> = self.bounds.iter_bounds().collect();
+        let grid = match (&self.gp_state, recorder.grid_points()) {
+            (GPState::Nominal, Some((x1, x2, pts))) if self.config.fixed.is_empty() => {
+                let mut local_pts = pts.clone();
+                local_pts
+                    .columns_mut()
+                    .into_iter()
+                    .zip(pts.columns())
+                    .for_each(|(mut local, x)| local.assign(&self.gp.mem.x_prime(x)));
+
+                self.gp
+                    .predict(local_pts)
+                    .ok()
+                    .map(|(mean, std)| GridPrediction {
+                        mean: Array2::from_shape_fn((x2.len(), x1.len()), |(r, c)| {
+                            self.gp.mem.y_test(mean[(r * x1.len() + c, 0)])
+                        }),
+                        std: Array2::from_shape_fn((x2.len(), x1.len()), |(r, c)| {
+                            let i = r * x1.len() + c;
+                            self.gp.mem.y_test_std(mean[(i, 0)], std[(i, 0)])
+                        }),
+                        x1: x1.mapv(|x| bounds[0].untransform(&x)),
+                        x2: x2.mapv(|x| bounds[1].untransform(&x)),
+                    })
+            }
+            _ => None,
+        };
+
+        let untransform = |X: Array2<f_>| {
+            let mut X = X;
+            X.columns_mut()
+                .into_iter()
+                .for_each(|mut x| x.assign(&self.bounds.untransform(x.view())));
+            X
+        };
+
+        let trust_region = self.trust_region();
+        let frame = Frame {
+            n_samples: self.config.n_samples,
+            X: untransform(self.expand_X(self.gp.mem.X())),
+            y: self.gp.mem.y(),
+            trust_region: trust_region.as_ref().map(|tr| FrameRegion {
+                centre: tr.centre_untransformed(&self.bounds),
+                extent: tr.extent_untransformed(&self.bounds),
+                polygon: tr.corners_untransformed(&self.bounds),
+            }),
+            trust_region_transformed: trust_region,
+            suggestion: untransform(suggestion.clone()),
+            grid,
+        };
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.push(frame);
+        }
+    }
+
     fn state_transition(&mut self) {
         match &self.gp_state {
             GPState::Init(init_pts) => {
@@ -355,37 +1141,54 @@ impl<S: LABCATReadyState> LABCAT<S> {
     #[cfg(not(feature = "python"))]
     fn restart(&mut self, _err: anyhow::Error) {
         let mut gp = GP::new(
-            self.bounds.bounds_arr().to_owned(),
+            self.gp.bounds.clone(),
             self.config.beta,
-            self.config.prior_sigma,
+            self.gp.priors.clone(),
+        )
+        .with_fidelity(self.bounds.fidelity())
+        .with_categorical(self.gp.categorical.clone())
+        .with_output_warping(self.config.output_warping.clone())
+        .with_outlier_clipping(self.config.outlier_clipping.clone())
+        .with_forget_strategy(self.config.forget_strategy.clone())
+        .with_archive(self.config.archive_forgotten != Archiving::Off);
+
+        let init_points = self.gp.bounds.sample(
+            (self.init_pts_fn)(self.bounds.dim() - 1),
+            &self.config.initial_design,
         );
 
-        #[cfg(feature = "LHS")]
-        let init_points = self
-            .bounds
-            .bounds_arr()
-            .LHS_sample((self.init_pts_fn)(self.bounds.dim() - 1));
-        #[cfg(not(feature = "LHS"))]
-        let init_points = self
-            .bounds
-            .bounds_arr()
-            .random_sample((self.init_pts_fn)(self.bounds.dim() - 1));
-
         let gp_state = GPState::Init(init_points);
 
-        gp.mem.append(
+        let min_index = self.gp.mem.min_index();
+        gp.mem.append_with_fidelity(
             self.gp.mem.X_min().into_col(),
             Array1::from_elem((1,), self.gp.mem.y_min()),
+            Array1::from_elem((1,), self.gp.mem.cost()[min_index]),
+            self.gp.mem.fidelity_gap()[min_index],
         );
+        if let Some(archive) = self.gp.mem.archive_with_memory() {
+            match self.config.archive_forgotten {
+                Archiving::ReloadOnRestart => gp.mem.load_archive(&archive),
+                _ => gp.mem.restore_archive(archive),
+            }
+        }
         self.gp = gp;
         self.gp_state = gp_state;
+        self.config.last_improvement = Some((self.config.n_samples, self.gp.mem.y_min()));
     }
 
     #[cfg(not(feature = "python"))]
     fn _suggest(&mut self) -> Array2<f_> {
+        self.config.next_fidelity = None;
         self.state_transition();
         match self.gp_state.clone() {
-            GPState::Init(init_pts) => init_pts,
+            GPState::Init(init_pts) => {
+                let mut X = self.expand_X(self.apply_context(init_pts));
+                X.columns_mut()
+                    .into_iter()
+                    .for_each(|mut col| col.assign(&self.bounds.snap(col.view())));
+                X
+            }
             GPState::Nominal => match self.step_alogrithm() {
                 Ok(Arr) => Arr,
                 Err(err) => {
@@ -398,59 +1201,208 @@ impl<S: LABCATReadyState> LABCAT<S> {
 
     #[cfg(not(feature = "python"))]
     fn step_alogrithm(&mut self) -> Result<Array2<f_>> {
-        let min = self.gp.mem.X.column(self.gp.mem.min_index()).to_owned();
-        self.gp.mem.recenter_X(min.view());
+        let centre = self.centre()?;
+        self.gp.mem.recenter_X(centre.view());
 
         self.gp.mem.rescale_y();
 
-        #[cfg(feature = "PCA")]
-        self.gp.mem.rotate_X()?;
+        if self.config.rotation {
+            self.gp
+                .mem
+                .rotate_X(&self.config.pca_weighting, self.config.rotation_smoothing)?;
+        }
 
         self.gp.fit()?;
 
         // self.gp.optimize_thetas()?;
-        match self.gp.optimize_thetas() {
-            Ok(_) => (),
+        match self.optimize_thetas() {
+            Ok(log_lik) => self.config.log_lik = Some(log_lik),
             Err(_) => {
+                self.config.log_lik = None;
                 self.gp.kernel.whiten_l();
                 self.gp.fit()?;
             }
         };
 
+        let thetas = self.sample_thetas()?;
+        let X_scale = self.gp.mem.X_scale().diag().to_owned();
+
         self.gp
             .mem
-            .rescale_X(self.gp.kernel.l(), Some(self.config.prior_sigma));
+            .rescale_X(self.gp.kernel.l(), self.config.rescale_cap);
         self.gp.kernel.whiten_l();
 
+        let (min_len, max_len) = self.trust_region_lens();
         self.gp
             .mem
-            .forget(&self.gp.search_dom, (self.forget_fn)(self.bounds.dim()));
+            .clamp_X_scale(&self.gp.search_dom, min_len.as_ref(), max_len.as_ref());
+        let thetas = self.rescale_thetas(thetas, &X_scale);
+
+        self.forget((self.forget_fn)(self.bounds.dim()));
 
         self.gp.fit()?;
-        let (_, ei_pt) = self.gp.optimize_ei(10 * self.bounds.dim())?;
-        let scaled_ei_pt = self.gp.mem.x_test(ei_pt.view()).into_col();
+        let ei_pt = self.acquire(&thetas)?;
+        self.config.next_fidelity = self.select_fidelity(ei_pt.view());
+        let scaled_ei_pt = self.suggestion(ei_pt.view()).into_col();
         Ok(scaled_ei_pt)
     }
 
-    fn _observe(&mut self, X: Array2<f_>, y: Array1<f_>) {
+    /// Optimises the thetas with the configured optimiser, returning the log marginal likelihood.
+    fn optimize_thetas(&mut self) -> Result<f_> {
+        match self.config.theta_optimizer {
+            ThetaOptimizer::Newton => {
+                self.gp.optimize_thetas()?;
+                self.gp.fit()?;
+                Ok(self.gp.log_lik())
+            }
+            ThetaOptimizer::Lbfgs {
+                restarts,
+                max_iter,
+                tol,
+            } => Ok(self.gp.optimize_thetas_lbfgs(restarts, max_iter, tol)?),
+        }
+    }
+
+    /// Posterior samples of the thetas if fully Bayesian hyperparameters are enabled, failing if
+    /// the GP cannot be refitted after sampling.
+    fn sample_thetas(&mut self) -> Result<Vec<Array1<f_>>> {
+        match self.config.theta_sampling.clone() {
+            Some(sampling) => Ok(self.gp.sample_thetas(&sampling)?),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Expresses `thetas` of the local coordinates with the scales `X_scale` in the current local
+    /// coordinates, dividing out the rescaling of the inputs since.
+    fn rescale_thetas(&self, thetas: Vec<Array1<f_>>, X_scale: &Array1<f_>) -> Vec<Array1<f_>> {
+        let ratio = self.gp.mem.X_scale().diag().to_owned() / X_scale;
+
+        thetas
+            .into_iter()
+            .map(|mut thetas| {
+                thetas
+                    .slice_mut(s![2..])
+                    .zip_mut_with(&ratio, |l, r| *l /= r);
+                thetas
+            })
+            .collect()
+    }
+
+    /// Maximiser of the acquisition function, with the expected improvement averaged over the GPs
+    /// with the sampled `thetas`, if any.
+    fn acquire(&self, thetas: &[Array1<f_>]) -> Result<Array1<f_>> {
+        let n = 10 * self.bounds.dim();
+        let mut X = self.unevaluated(self.gp.candidates(n));
+
+        // Once every point that the bounds can take in the trust region has been evaluated,
+        // candidates are drawn from the whole bounds
+        if X.ncols() == 0 {
+            let mut global = self.gp.bounds.random_sample(n);
+            global
+                .columns_mut()
+                .into_iter()
+                .for_each(|mut col| col.assign(&self.gp.mem.x_prime(col.view())));
+            X = self.unevaluated(global);
+        }
+
+        let y_best = match self.config.context {
+            Some(_) => self.contextual_best()?.1,
+            None => self.gp.mem.y_prime_min(),
+        };
+
+        let models: Vec<_> = thetas
+            .iter()
+            .filter_map(|thetas| self.gp.with_thetas(thetas).ok())
+            .collect();
+        if models.is_empty() && !thetas.is_empty() {
+            bail!("GP could not be fitted with any of the sampled thetas!")
+        }
+        let ei = |x: ArrayView1<f_>| match models.is_empty() {
+            true => self.gp.ei_with_incumbent(x, y_best),
+            false => {
+                models
+                    .iter()
+                    .map(|gp| gp.ei_with_incumbent(x, y_best))
+                    .sum::<f_>()
+                    / models.len() as f_
+            }
+        };
+
+        let (_, pt) = match self.config.acquisition {
+            Acquisition::EI => self.gp.optimize_acq_over(X, ei)?,
+            Acquisition::EIPerCost => {
+                let cost_model = self.gp.cost_model()?;
+                self.gp
+                    .optimize_acq_over(X, |x| match cost_model.predict_cost(x) {
+                        Ok(cost) => ei(x) / cost,
+                        Err(_) => f_::NAN,
+                    })?
+            }
+        };
+
+        Ok(pt)
+    }
+
+    fn _observe(
+        &mut self,
+        X: Array2<f_>,
+        y: Array1<f_>,
+        c: Option<Array1<f_>>,
+        fidelity: Option<usize>,
+    ) {
+        let (level_cost, gap) = match (fidelity, self.bounds.fidelity()) {
+            (Some(level), Some(fid)) => {
+                if level > fid.top() {
+                    panic!("Fidelity level {} out of range!", level)
+                }
+                (fid.costs()[level], fid.top() - level)
+            }
+            (None, Some(fid)) => (fid.costs()[fid.top()], 0),
+            (Some(_), None) => panic!("Bounds have no fidelity parameter!"),
+            (None, None) => (1.0, 0),
+        };
+        let c = c.unwrap_or_else(|| Array1::from_elem((y.len(),), level_cost));
+
+        // Inactive conditional parameters are modelled at their imputed values
+        let mut X = X;
+        X.columns_mut()
+            .into_iter()
+            .for_each(|mut col| col.assign(&self.bounds.impute(col.view())));
+        let X = self.reduce_X(X);
         self.config.n_samples += X.ncols();
+        self.config.total_cost += c.sum();
 
         self.state_transition();
         match self.gp_state.clone() {
             GPState::Init(mut init_pts) => {
                 let s = Slice::new(0, Some(-(X.ncols() as isize)), 1);
-                self.gp.mem.append(X, y);
+                self.gp.mem.append_with_fidelity(X, y, c, gap);
                 init_pts.slice_axis_inplace(Axis(1), s);
                 self.gp_state = GPState::Init(init_pts);
             }
             GPState::Nominal => {
-                self.gp.mem.append(X, y);
+                self.gp.mem.append_with_fidelity(X, y, c, gap);
             } // GPState::Restart(_) => panic!("Should never trigger"),
         }
+
+        self.update_stagnation();
+    }
+
+    fn update_stagnation(&mut self) {
+        let y_min = self.gp.mem.y_min();
+        let rel_improvement = match self.config.max_stagnation {
+            Some((_, rel)) => rel,
+            None => 0.0,
+        };
+
+        match self.config.last_improvement {
+            Some((_, best)) if best - y_min <= rel_improvement * best.abs() => (),
+            _ => self.config.last_improvement = Some((self.config.n_samples, y_min)),
+        }
     }
 
     pub fn _check_converged(&self) -> Option<TermCond> {
-        if self.gp.mem.y_scaling() < self.config.target_tol {
+        if self.gp.mem.y_range() < self.config.target_tol {
             if self.config.target_tol == f_::EPSILON {
                 return Some(TermCond::MachineEpsilonReached);
             } else {
@@ -476,6 +1428,33 @@ impl<S: LABCATReadyState> LABCAT<S> {
             }
         }
 
+        if self
+            .config
+            .max_cost
+            .is_some_and(|max| self.config.total_cost >= max)
+        {
+            return Some(TermCond::MaxCostReached);
+        }
+
+        let stagnated = match (self.config.max_stagnation, self.config.last_improvement) {
+            (Some((n, _)), Some((last, _))) => self.config.n_samples - last >= n,
+            _ => false,
+        };
+        if stagnated {
+            return Some(TermCond::Stagnated);
+        }
+
+        let collapsed = match (&self.config.x_tol, self.trust_region()) {
+            (Some(tol), Some(tr)) => {
+                let extent = tr.extent_untransformed(&self.bounds);
+                extent.iter().zip(tol.iter()).all(|(ext, tol)| ext < tol)
+            }
+            _ => false,
+        };
+        if collapsed {
+            return Some(TermCond::TrustRegionCollapsed);
+        }
+
         None
     }
 }
@@ -487,28 +1466,174 @@ impl LABCAT<Manual> {
             gp: self.gp,
             gp_state: self.gp_state,
             bounds: self.bounds,
-            target_fn: Some(f),
+            target_fn: Some(TargetFn::Plain(f)),
+            init_pts_fn: self.init_pts_fn,
+            forget_fn: self.forget_fn,
+            config: self.config,
+            config_state: PhantomData,
+            recorder: self.recorder,
+        }
+    }
+
+    /// Sets a target function that returns both the objective function values and the (positive)
+    /// evaluation costs of the samples.
+    pub fn set_target_fn_with_cost(self, f: CostTargetFn) -> LABCAT<Auto> {
+        LABCAT {
+            gp: self.gp,
+            gp_state: self.gp_state,
+            bounds: self.bounds,
+            target_fn: Some(TargetFn::WithCost(f)),
+            init_pts_fn: self.init_pts_fn,
+            forget_fn: self.forget_fn,
+            config: self.config,
+            config_state: PhantomData,
+            recorder: self.recorder,
+        }
+    }
+
+    /// Sets a target function of a single point as a struct of the search space `T`, which must
+    /// have the same bounds as the bounds of the algorithm, e.g. from [`LABCAT::new_typed`].
+    pub fn set_target_fn_typed<T: SearchSpace + 'static>(self, f: fn(T) -> f_) -> LABCAT<Auto> {
+        let labels = |bounds: &Bounds<Ready>| -> Vec<String> {
+            bounds
+                .iter_bounds()
+                .map(|b| b.label().to_string())
+                .collect()
+        };
+        if labels(&self.bounds) != labels(&T::bounds()) {
+            panic!("Bounds of the search space do not match the bounds of the algorithm!")
+        }
+
+        let f: LabelledTargetFn =
+            Arc::new(move |X: Vec<BoundReprs>| X.into_iter().map(|x| f(T::from(x))).collect());
+
+        LABCAT {
+            gp: self.gp,
+            gp_state: self.gp_state,
+            bounds: self.bounds,
+            target_fn: Some(TargetFn::Labelled(f)),
             init_pts_fn: self.init_pts_fn,
             forget_fn: self.forget_fn,
             config: self.config,
             config_state: PhantomData,
+            recorder: self.recorder,
+        }
+    }
+
+    /// Sets a target function that is evaluated at the fidelity level selected by the algorithm.
+    pub fn set_target_fn_with_fidelity(self, f: FidelityTargetFn) -> LABCAT<Auto> {
+        if self.bounds.fidelity().is_none() {
+            panic!("Bounds have no fidelity parameter!")
+        }
+
+        LABCAT {
+            gp: self.gp,
+            gp_state: self.gp_state,
+            bounds: self.bounds,
+            target_fn: Some(TargetFn::WithFidelity(f)),
+            init_pts_fn: self.init_pts_fn,
+            forget_fn: self.forget_fn,
+            config: self.config,
+            config_state: PhantomData,
+            recorder: self.recorder,
         }
     }
 
     pub fn observations(&self) -> (Array2<f_>, Array1<f_>) {
-        (self.gp.mem.X(), self.gp.mem.y())
+        (self.expand_X(self.gp.mem.X()), self.gp.mem.y())
+    }
+
+    /// Observations forgotten since the last restart, or `None` if they are not archived.
+    pub fn archived_observations(&self) -> Option<(Array2<f_>, Array1<f_>)> {
+        let archive = self.gp.mem.archive()?;
+        Some((self.expand_X(archive.X.clone()), archive.y.clone()))
     }
 
     pub fn thetas(&self) -> &Array1<f_> {
         self.gp.kernel.thetas()
     }
 
+    /// Log marginal likelihood of the GP after the hyperparameters were last optimised, or `None`
+    /// if the last optimisation failed.
+    pub fn log_marginal_likelihood(&self) -> Option<f_> {
+        self.config.log_lik
+    }
+
     pub fn suggest(&mut self) -> Array2<f_> {
-        self._suggest()
+        let suggestion = self._suggest();
+        self.record_frame(&suggestion);
+        suggestion
+    }
+
+    /// Suggested points in the units of the bounds, leaving out inactive parameters.
+    pub fn suggest_labelled(&mut self) -> Vec<BoundReprs> {
+        let suggestion = self.suggest();
+        self.reprs(&suggestion)
+    }
+
+    /// Suggested points as structs of the search space `T`, which should match the bounds.
+    pub fn suggest_typed<T: SearchSpace>(&mut self) -> Vec<T> {
+        self.suggest_labelled().into_iter().map(T::from).collect()
+    }
+
+    /// Suggestion with the context variables of the bounds set to `context`, in the order that they were added.
+    pub fn suggest_with_context(&mut self, context: Array1<f_>) -> Array2<f_> {
+        if context.len() != self.bounds.context_dims().len() {
+            panic!("Length of context does not match the number of context variables!")
+        }
+
+        self.config.context = Some(context);
+        let suggestion = self.suggest();
+        self.config.context = None;
+
+        suggestion
+    }
+
+    /// Suggestion together with the fidelity level at which it should be evaluated.
+    pub fn suggest_with_fidelity(&mut self) -> (Array2<f_>, usize) {
+        let suggestion = self.suggest();
+        let fidelity = self
+            .suggested_fidelity()
+            .expect("Bounds have no fidelity parameter!");
+
+        (suggestion, fidelity)
+    }
+
+    /// Fixes the parameter labelled `label` at `value`, removing it from the model and the trust
+    /// region and projecting the observations so far onto `value`. Suggestions keep the full
+    /// dimension of the bounds, with the parameter at `value`.
+    pub fn fix_parameter(&mut self, label: &str, value: f_) {
+        self._fix_parameter(label, value)
+    }
+
+    /// Releases a parameter fixed with [`LABCAT::fix_parameter`], with the observations so far
+    /// taken to lie at its fixed value.
+    pub fn unfix_parameter(&mut self, label: &str) {
+        self._unfix_parameter(label)
     }
 
     pub fn observe(&mut self, X: Array2<f_>, y: Array1<f_>) {
-        self._observe(X, y)
+        self._observe(X, y, None, None)
+    }
+
+    /// Observations of points in the units of the bounds, e.g. from [`LABCAT::suggest_labelled`].
+    pub fn observe_labelled(&mut self, X: Vec<BoundReprs>, y: Array1<f_>) {
+        let X = self.parse_reprs(X);
+        self._observe(X, y, None, None)
+    }
+
+    /// Observations of points as structs of the search space `T`, e.g. from [`LABCAT::suggest_typed`].
+    pub fn observe_typed<T: SearchSpace>(&mut self, X: Vec<T>, y: Array1<f_>) {
+        self.observe_labelled(X.into_iter().map(Into::into).collect(), y)
+    }
+
+    pub fn observe_with_cost(&mut self, X: Array2<f_>, y: Array1<f_>, c: Array1<f_>) {
+        self._observe(X, y, Some(c), None)
+    }
+
+    /// Observations made at the given fidelity level, with the cost of the level.
+    pub fn observe_with_fidelity(&mut self, X: Array2<f_>, y: Array1<f_>, fidelity: usize) {
+        self._observe(X, y, None, Some(fidelity))
     }
 
     pub fn check_converged(&self) -> Option<TermCond> {
@@ -549,6 +1674,36 @@ impl LABCAT<Auto> {
         self
     }
 
+    /// Budget of the summed evaluation costs, see [`LABCAT::set_target_fn_with_cost`].
+    pub fn max_cost(mut self, budget: f_) -> Self {
+        self.config.max_cost = Some(budget);
+        self
+    }
+
+    /// Terminates once the extent of the trust region along every dimension falls below `tol`, in
+    /// the original units of the bounds.
+    pub fn x_tol(mut self, tol: f_) -> Self {
+        self.config.x_tol = Some(Array1::from_elem((self.bounds.dim(),), tol));
+        self
+    }
+
+    /// Terminates (or restarts if enabled) when the best objective function value has not improved
+    /// by a relative amount of `rel_improvement` in `n` evaluations.
+    pub fn max_stagnation(mut self, n: usize, rel_improvement: f_) -> Self {
+        self.config.max_stagnation = Some((n, rel_improvement));
+        self
+    }
+
+    /// Per-dimension version of [`LABCAT::x_tol`].
+    pub fn x_tol_per_dim(mut self, tol: Array1<f_>) -> Self {
+        if tol.len() != self.bounds.dim() {
+            panic!("Length of x tolerances does not match bounds dim!")
+        }
+
+        self.config.x_tol = Some(tol);
+        self
+    }
+
     pub fn run(mut self) -> OptimizationSummary {
         let print = self.config.auto_print.is_some();
         if print {
@@ -557,8 +1712,17 @@ impl LABCAT<Auto> {
         }
         loop {
             let suggest = self._suggest();
-            let samples = (self.target_fn.unwrap())(&suggest);
-            self._observe(suggest, samples);
+            self.record_frame(&suggest);
+            let fidelity = match self.target_fn {
+                Some(TargetFn::WithFidelity(_)) => self.suggested_fidelity(),
+                _ => None,
+            };
+            let (samples, cost) = self
+                .target_fn
+                .as_ref()
+                .expect("LABCAT<Auto> has a target function")
+                .eval(&suggest, fidelity, |X| self.reprs(X));
+            self._observe(suggest, samples, cost, fidelity);
 
             if print
                 && self.config.n_samples % self.config.auto_print.expect("Already checked option")
@@ -570,22 +1734,20 @@ impl LABCAT<Auto> {
             if let Some(term) = self._check_converged() {
                 if self.config.restarts {
                     match term {
-                        TermCond::MaxItersReached => {
-                            println!("{}", self.bottom_border());
-                            return OptimizationSummary {
-                                term_reason: term,
-                                n_samples: self.config.n_samples,
-                                min_x: self.gp.mem.X_min(),
-                                min_y: self.gp.mem.y_min(),
-                            };
-                        }
-                        TermCond::MaxTimeReached => {
-                            println!("{}", self.bottom_border());
+                        TermCond::MaxItersReached
+                        | TermCond::MaxTimeReached
+                        | TermCond::MaxCostReached => {
+                            if print {
+                                println!("{}", self.bottom_border());
+                            }
+
                             return OptimizationSummary {
                                 term_reason: term,
                                 n_samples: self.config.n_samples,
-                                min_x: self.gp.mem.X_min(),
+                                min_x: self.X_min(),
                                 min_y: self.gp.mem.y_min(),
+                                total_cost: self.config.total_cost,
+                                recording: self.recorder.take(),
                             };
                         }
                         _ => self.restart(anyhow::format_err!("Restart after converge!")),
@@ -598,8 +1760,10 @@ impl LABCAT<Auto> {
                     return OptimizationSummary {
                         term_reason: term,
                         n_samples: self.config.n_samples,
-                        min_x: self.gp.mem.X_min(),
+                        min_x: self.X_min(),
                         min_y: self.gp.mem.y_min(),
+                        total_cost: self.config.total_cost,
+                        recording: self.recorder.take(),
                     };
                 }
             };
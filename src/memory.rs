@@ -6,6 +6,8 @@ use rand;
 use crate::{
     bounds_array::ArrayBounds,
     f_,
+    output_warping::{OutputWarp, OutputWarping},
+    trust_region::TrustRegion,
     utils::{Array1Utils, Array2Utils, ArrayBaseFloatUtils},
 };
 
@@ -15,6 +17,186 @@ pub enum MemoryState {
     Unfitted,
 }
 
+/// Weighting scheme of the observations used in the weighted PCA of [`Memory::rotate_X`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum PCAWeighting {
+    /// Weights of `1 - y_i` on the rescaled outputs.
+    #[default]
+    Linear,
+    /// Log-rank weights of the best half of the observations, as in CMA-ES.
+    Rank,
+    /// Weights of `exp(-y_i / temperature)` on the rescaled outputs.
+    Softmin(f_),
+    /// Equal weights for the `k` best observations, zero otherwise.
+    TopK(usize),
+}
+
+impl PCAWeighting {
+    pub fn weights(&self, y: &Array1<f_>) -> Array1<f_> {
+        let mut order: Vec<usize> = (0..y.len()).collect();
+        order.sort_by(|a, b| y[*a].total_cmp(&y[*b]));
+        let mut rank = Array1::zeros((y.len(),));
+        order
+            .iter()
+            .enumerate()
+            .for_each(|(r, i)| rank[*i] = (r + 1) as f_);
+
+        match self {
+            PCAWeighting::Linear => y.map(|y_i| 1.0 - y_i),
+            PCAWeighting::Rank => {
+                let mu = (y.len() as f_ / 2.0).max(1.0);
+                rank.map(|r| match *r <= mu {
+                    true => (mu + 0.5).ln() - r.ln(),
+                    false => 0.0,
+                })
+            }
+            PCAWeighting::Softmin(temp) => y.map(|y_i| (-y_i / temp).exp()),
+            PCAWeighting::TopK(k) => rank.map(|r| match *r <= *k as f_ {
+                true => 1.0,
+                false => 0.0,
+            }),
+        }
+    }
+}
+
+/// Clipping of outlying large outputs before they are warped and rescaled, so that a few failed
+/// evaluations do not squash the other outputs. Small outputs are never clipped.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum OutlierClipping {
+    #[default]
+    None,
+    /// Clips outputs above `median + k * sigma`, with `sigma` estimated from the median absolute
+    /// deviation, or from the mean absolute deviation if more than half of the outputs are equal
+    /// to the median, Iglewicz and Hoaglin (1993).
+    Mad(f_),
+    /// Clips outputs above the `q` quantile of the outputs.
+    Quantile(f_),
+}
+
+impl OutlierClipping {
+    /// Largest output that is not clipped, or `None` if no outputs are clipped.
+    pub fn threshold(&self, y: &Array1<f_>) -> Option<f_> {
+        let mut sorted = y.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let threshold = match self {
+            OutlierClipping::None => return None,
+            OutlierClipping::Mad(k) => {
+                let median = Self::quantile(&sorted, 0.5)?;
+                let mut dev: Vec<f_> = sorted.iter().map(|y| (y - median).abs()).collect();
+                dev.sort_by(|a, b| a.total_cmp(b));
+
+                let sigma = match Self::quantile(&dev, 0.5)? {
+                    // Plateaued outputs, which would clip every output above the plateau
+                    0.0 => 1.2533 * dev.iter().sum::<f_>() / dev.len() as f_,
+                    mad => 1.4826 * mad,
+                };
+                median + k * sigma
+            }
+            OutlierClipping::Quantile(q) => Self::quantile(&sorted, *q)?,
+        };
+
+        match sorted.last() {
+            Some(max) if *max > threshold && threshold > sorted[0] => Some(threshold),
+            _ => None,
+        }
+    }
+
+    /// Linearly interpolated quantile `q` of `sorted`.
+    fn quantile(sorted: &[f_], q: f_) -> Option<f_> {
+        let pos = q * (sorted.len() as f_ - 1.0);
+        let (lower, upper) = (pos.floor() as usize, pos.ceil() as usize);
+
+        Some(sorted.get(lower)? + (pos - lower as f_) * (sorted.get(upper)? - sorted.get(lower)?))
+    }
+}
+
+/// Order in which the observations outside the local search domain are forgotten.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ForgetStrategy {
+    /// Oldest observations first.
+    #[default]
+    Oldest,
+    /// Observations farthest from the centre of the trust region first, i.e. by Mahalanobis
+    /// distance in the metric of the trust region.
+    Farthest,
+    /// Observations with the worst outputs first.
+    Worst,
+    /// Observations closest to their nearest neighbour first, keeping a space-filling subset.
+    SpaceFilling,
+}
+
+impl ForgetStrategy {
+    pub fn parse_forget_strategy(strategy: &str) -> Option<ForgetStrategy> {
+        match strategy.to_lowercase().as_str() {
+            "oldest" => Some(ForgetStrategy::Oldest),
+            "farthest" => Some(ForgetStrategy::Farthest),
+            "worst" => Some(ForgetStrategy::Worst),
+            "space_filling" => Some(ForgetStrategy::SpaceFilling),
+            _ => None,
+        }
+    }
+}
+
+/// Archiving of the observations forgotten by the memory.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Archiving {
+    /// Forgotten observations are discarded.
+    #[default]
+    Off,
+    /// Forgotten observations are archived, and the archive is kept over restarts without being
+    /// reloaded into the memory.
+    Keep,
+    /// Forgotten observations are archived, and the archive is reloaded into the memory on restart.
+    ReloadOnRestart,
+}
+
+impl Archiving {
+    pub fn parse_archiving(archiving: &str) -> Option<Archiving> {
+        match archiving.to_lowercase().as_str() {
+            "off" | "none" => Some(Archiving::Off),
+            "keep" => Some(Archiving::Keep),
+            "reload_on_restart" | "reload" => Some(Archiving::ReloadOnRestart),
+            _ => None,
+        }
+    }
+}
+
+/// Observations forgotten by the memory, with the inputs in the space of the bounds.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub X: Array2<f_>,
+    pub y: Array1<f_>,
+    pub c: Array1<f_>,
+    pub fidelity_gap: Vec<usize>,
+}
+
+impl Archive {
+    pub fn new(d: usize) -> Archive {
+        Archive {
+            X: Array2::zeros((d, 0)),
+            y: Array1::zeros((0,)),
+            c: Array1::zeros((0,)),
+            fidelity_gap: vec![],
+        }
+    }
+
+    pub fn n(&self) -> usize {
+        self.X.ncols()
+    }
+
+    pub fn push(&mut self, x: ArrayView1<f_>, y: f_, c: f_, fidelity_gap: usize) {
+        self.X.push(Axis(1), x).expect("push should never fail");
+        self.y
+            .push(Axis(0), ndarray::aview0(&y))
+            .expect("push should never fail");
+        self.c
+            .push(Axis(0), ndarray::aview0(&c))
+            .expect("push should never fail");
+        self.fidelity_gap.push(fidelity_gap);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Memory {
     state: MemoryState,
@@ -36,6 +218,19 @@ pub struct Memory {
     pub y: Array1<f_>,
     y_offset: f_,
     y_scaling: f_,
+    y_raw: Array1<f_>, // outputs as observed, before warping and rescaling
+    y_range: f_,
+    warping: OutputWarping,
+    warp: OutputWarp,
+    clipping: OutlierClipping,
+    y_clip: Option<f_>, // threshold of the outputs when they were last rescaled
+
+    forget_strategy: ForgetStrategy,
+    archive: Option<Archive>, // forgotten observations, if they are archived
+
+    ln_c: Array1<f_>,         // log of the evaluation cost of every observation
+    fidelity_gap: Vec<usize>, // levels below the highest fidelity of every observation
+    categorical: Vec<usize>, // untransformed dimensions, with the category codes as local coordinates
 }
 
 impl Memory {
@@ -54,6 +249,19 @@ impl Memory {
             y: Array1::zeros((0,)),
             y_offset: 0.0,
             y_scaling: 1.0,
+            y_raw: Array1::zeros((0,)),
+            y_range: 1.0,
+            warping: OutputWarping::None,
+            warp: OutputWarp::identity(),
+            clipping: OutlierClipping::None,
+            y_clip: None,
+
+            forget_strategy: ForgetStrategy::Oldest,
+            archive: None,
+
+            ln_c: Array1::zeros((0,)),
+            fidelity_gap: vec![],
+            categorical: vec![],
         }
     }
 
@@ -69,6 +277,40 @@ impl Memory {
         self.state = MemoryState::Unfitted
     }
 
+    /// Excludes the categorical dimensions from recentering, rescaling and rotation.
+    pub fn set_categorical(&mut self, dims: Vec<usize>) {
+        self.categorical = dims;
+    }
+
+    /// Warping of the outputs, fitted to the outputs in memory when they are rescaled.
+    pub fn set_output_warping(&mut self, warping: OutputWarping) {
+        self.warping = warping;
+    }
+
+    /// Clipping of outlying outputs, updated when the outputs are rescaled.
+    pub fn set_outlier_clipping(&mut self, clipping: OutlierClipping) {
+        self.clipping = clipping;
+    }
+
+    pub fn set_forget_strategy(&mut self, strategy: ForgetStrategy) {
+        self.forget_strategy = strategy;
+    }
+
+    /// Keeps the forgotten observations in an archive.
+    pub fn set_archive(&mut self, archive: bool) {
+        self.archive = match archive {
+            true => Some(Archive::new(self.X.nrows())),
+            false => None,
+        };
+    }
+
+    /// Dimensions that are not categorical.
+    pub fn continuous_dims(&self) -> Vec<usize> {
+        (0..self.X.nrows())
+            .filter(|d| !self.categorical.contains(d))
+            .collect()
+    }
+
     pub fn reset_transform(&mut self) {
         self.X = self.X();
 
@@ -85,31 +327,32 @@ impl Memory {
 
         self.y_offset = 0.0;
         self.y_scaling = 1.0;
+        self.y_range = 1.0;
+        self.warp = OutputWarp::identity();
+        self.y_clip = None;
 
         self.set_unfitted();
     }
 
     pub fn recenter_X(&mut self, x: ArrayView1<f_>) {
-        self.X = self.X.clone().sub_column_view(&x);
+        let mut x = x.to_owned();
+        self.categorical.iter().for_each(|d| x[*d] = 0.0);
+
+        self.X = self.X.clone().sub_column_view(&x.view());
 
         self.X_offset = self.X_offset.clone() + self.X_rotate.dot(&self.X_scale).dot(&x);
 
         self.set_unfitted();
     }
 
-    pub fn rescale_X(&mut self, l: ArrayView1<f_>, prior_sigma_cap: Option<f_>) {
-        fn cap_l(l: &f_, prior_sigma: &f_) -> f_ {
-            match l {
-                l if *l >= 1.0 + 3.0 * prior_sigma => 1.0 + 3.0 * prior_sigma,
-                l if *l <= 1.0 - 3.0 * prior_sigma => 1.0 - 3.0 * prior_sigma,
-                _ => *l,
-            }
-        }
-
-        let l = match prior_sigma_cap {
-            Some(sig) => l.map(|l| cap_l(l, &sig)),
+    /// Rescales the local coordinates by the length scales `l`, each limited to `[1 - cap, 1 + cap]`
+    /// if a cap is given.
+    pub fn rescale_X(&mut self, l: ArrayView1<f_>, cap: Option<f_>) {
+        let mut l = match cap {
+            Some(cap) => l.map(|l| l.clamp(1.0 - cap, 1.0 + cap)),
             None => l.to_owned(),
         };
+        self.categorical.iter().for_each(|d| l[*d] = 1.0);
 
         let l_recip = Array2::from_diag(&l.map(|val| 1.0 / val));
         let l = Array2::from_diag(&l);
@@ -122,12 +365,61 @@ impl Memory {
         self.set_unfitted();
     }
 
-    pub fn rotate_X(&mut self) -> Result<()> {
-        let W = Array2::from_diag(&self.y.map(|y_i| 1.0 - y_i));
+    /// Rescales the local coordinates so that the side lengths of the trust region spanned by
+    /// `search_dom` lie between `min` and `max` along each of its axes, in the transformed space
+    /// of the bounds.
+    pub fn clamp_X_scale(
+        &mut self,
+        search_dom: &ArrayBounds,
+        min: Option<&Array1<f_>>,
+        max: Option<&Array1<f_>>,
+    ) {
+        let sides = self.X_scale.diag().to_owned() * search_dom.axes_len() * 2.0;
+
+        let mut ratio = Array1::from_shape_fn((sides.len(),), |i| {
+            let side = sides[i];
+            let clamped = match (min.map(|min| min[i]), max.map(|max| max[i])) {
+                (Some(min), _) if side < min => min,
+                (_, Some(max)) if side > max => max,
+                _ => side,
+            };
+            clamped / side
+        });
+        self.categorical.iter().for_each(|d| ratio[*d] = 1.0);
+
+        if ratio.iter().any(|r| *r != 1.0) {
+            self.rescale_X(ratio.view(), None);
+        }
+    }
+
+    pub fn rotate_X(&mut self, weighting: &PCAWeighting, smoothing: Option<f_>) -> Result<()> {
+        let W = Array2::from_diag(&weighting.weights(&self.y));
 
-        let (u, _, _) = self.X_scale.dot(&self.X).dot(&W).svd(true, false)?;
+        // Only the continuous dimensions are rotated
+        let cont = self.continuous_dims();
+        if cont.is_empty() {
+            return Ok(());
+        }
+
+        let (u_cont, _, _) = self
+            .X_scale
+            .dot(&self.X)
+            .dot(&W)
+            .select(Axis(0), &cont)
+            .svd(true, false)?;
         // let (u, _, _) = self.X.svd(true, false)?;
-        let u = u.expect("Unwrap of U should not fail");
+        let mut u_cont = u_cont.expect("Unwrap of U should not fail");
+
+        if let Some(alpha) = smoothing {
+            u_cont = Self::smooth_rotation(u_cont, alpha)?;
+        }
+
+        let mut u = Array2::eye(self.X.nrows());
+        cont.iter().enumerate().for_each(|(i, d_i)| {
+            cont.iter()
+                .enumerate()
+                .for_each(|(j, d_j)| u[(*d_i, *d_j)] = u_cont[(i, j)])
+        });
 
         self.X_rotate_inv = u.t().dot(&self.X_rotate_inv);
         self.X_rotate = self.X_rotate.dot(&u);
@@ -146,7 +438,55 @@ impl Memory {
         Ok(())
     }
 
+    /// Exponential moving average between the current rotation (identity) and a new rotation `u`,
+    /// projected back onto the closest orthogonal matrix.
+    fn smooth_rotation(mut u: Array2<f_>, alpha: f_) -> Result<Array2<f_>> {
+        let d = u.nrows();
+
+        // match principal axes to the closest current axes, as SVD does not preserve order or sign
+        let mut free_axes: Vec<usize> = (0..d).collect();
+        let mut aligned = Array2::zeros((d, d));
+        for col in u.columns() {
+            let (pos, axis) = free_axes
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| col[**a].abs().total_cmp(&col[**b].abs()))
+                .map(|(pos, axis)| (pos, *axis))
+                .expect("Should never fail, axes are matched one to one");
+            free_axes.remove(pos);
+
+            let sign = match col[axis] < 0.0 {
+                true => -1.0,
+                false => 1.0,
+            };
+            aligned.column_mut(axis).assign(&col.mapv(|val| sign * val));
+        }
+        u = aligned;
+
+        let blend = Array2::<f_>::eye(d) * (1.0 - alpha) + u * alpha;
+        let (p, _, q_t) = blend.svd(true, true)?;
+
+        Ok(p.expect("Unwrap of U should not fail")
+            .dot(&q_t.expect("Unwrap of V^T should not fail")))
+    }
+
+    /// Clips the outputs in memory, refits the output warping to them and rescales the warped
+    /// outputs to [0, 1].
     pub fn rescale_y(&mut self) {
+        self.y_clip = self.clipping.threshold(&self.y_raw);
+        let y = self.y_raw.mapv(|y| self.clip(y));
+
+        self.warp = OutputWarp::fit(&self.warping, &y);
+        self.y = y.mapv(|y| self.warp.warp(y));
+        self.y_offset = 0.0;
+        self.y_scaling = 1.0;
+
+        let range =
+            self.y_raw.max().copied().unwrap_or(0.0) - self.y_raw.min().copied().unwrap_or(0.0);
+        if range > 0.0 {
+            self.y_range = range;
+        }
+
         let min = self.y_prime_min();
 
         self.y -= min;
@@ -175,18 +515,46 @@ impl Memory {
         self.set_unfitted();
     }
 
-    pub fn append(&mut self, mut X: Array2<f_>, y: Array1<f_>) {
+    pub fn append(&mut self, X: Array2<f_>, y: Array1<f_>) {
+        let c = Array1::ones((y.len(),));
+        self.append_with_cost(X, y, c);
+    }
+
+    pub fn append_with_cost(&mut self, X: Array2<f_>, y: Array1<f_>, c: Array1<f_>) {
+        self.append_with_fidelity(X, y, c, 0);
+    }
+
+    /// Appends observations made `fidelity_gap` levels below the highest fidelity.
+    pub fn append_with_fidelity(
+        &mut self,
+        mut X: Array2<f_>,
+        y: Array1<f_>,
+        c: Array1<f_>,
+        fidelity_gap: usize,
+    ) {
         if !X.shape().ends_with(&[y.len()]) {
             panic!("Number of inputs and outputs differ");
         }
 
+        if c.len() != y.len() {
+            panic!("Number of outputs and costs differ");
+        }
+
+        if c.iter().any(|c| *c <= 0.0) {
+            panic!("Evaluation costs must be positive");
+        }
+
         // X = self.X_trans.dot(&X.sub_column(&self.X_offset));
         X = self
             .X_scale_inv
             .dot(&self.X_rotate_inv)
             .dot(&X.sub_column(&self.X_offset));
 
-        let mut y = y;
+        self.y_raw
+            .append(Axis(0), y.view())
+            .expect("append should never fail");
+
+        let mut y = y.mapv(|y| self.warp.warp(self.clip(y)));
         y -= self.y_offset;
         y /= self.y_scaling; //MOVE TO OWN FN?
 
@@ -196,19 +564,33 @@ impl Memory {
         self.y
             .append(Axis(0), y.view())
             .expect("append should never fail");
+        self.ln_c
+            .append(Axis(0), c.mapv(f_::ln).view())
+            .expect("append should never fail");
+        self.fidelity_gap
+            .extend(std::iter::repeat_n(fidelity_gap, y.len()));
 
         self.set_unfitted();
     }
 
     pub fn forget(&mut self, search_dom: &ArrayBounds, min: usize) {
+        let X = self.X.clone();
+        self.forget_by(search_dom, min, &X);
+    }
+
+    /// Forgets the observations of which the counterparts in `X_test`, in local coordinates, lie
+    /// outside `search_dom` along the continuous dimensions, in the order of the forget strategy.
+    pub fn forget_by(&mut self, search_dom: &ArrayBounds, min: usize, X_test: &Array2<f_>) {
         let n_to_forget = self.X.ncols().saturating_sub(min);
 
         if n_to_forget == 0 {
             return;
         }
 
-        let mut forget_indexes: Vec<usize> = self
-            .X
+        let cont = self.continuous_dims();
+        let search_dom = search_dom.select_dims(&cont);
+        let X_test = X_test.select(Axis(0), &cont);
+        let mut forget_indexes: Vec<usize> = X_test
             .columns()
             .into_iter()
             .enumerate()
@@ -216,14 +598,258 @@ impl Memory {
             .map(|(i, _)| i)
             .collect();
 
+        match self.forget_strategy {
+            ForgetStrategy::Oldest => (),
+            // The local coordinates are scaled by the trust region, so the distance relative to the
+            // search domain is the Mahalanobis distance in the metric of the trust region
+            ForgetStrategy::Farthest => {
+                let (centre, half_len) = (search_dom.midpoint(), search_dom.axes_len());
+                let dist = |i: &usize| ((&X_test.column(*i) - &centre) / &half_len).norm_l2();
+                forget_indexes.sort_by(|a, b| dist(b).total_cmp(&dist(a)))
+            }
+            ForgetStrategy::Worst => {
+                forget_indexes.sort_by(|a, b| self.y_raw[*b].total_cmp(&self.y_raw[*a]))
+            }
+            ForgetStrategy::SpaceFilling => {
+                forget_indexes = Self::crowded(&X_test, forget_indexes, n_to_forget)
+            }
+        }
+
         forget_indexes.truncate(n_to_forget);
 
-        self.y = self.y.clone().rem_at_index(forget_indexes.clone());
-        self.X = self.X.clone().rem_cols(forget_indexes);
+        self.archive_observations(&forget_indexes);
+        self.remove(forget_indexes);
+    }
+
+    /// Greedily picks `n` of the `candidates` that are closest to their nearest neighbour among
+    /// the columns of `X` that have not been picked.
+    fn crowded(X: &Array2<f_>, mut candidates: Vec<usize>, n: usize) -> Vec<usize> {
+        let mut picked = vec![];
+
+        while picked.len() < n && !candidates.is_empty() {
+            let nearest = |i: usize| {
+                (0..X.ncols())
+                    .filter(|j| *j != i && !picked.contains(j))
+                    .map(|j| (&X.column(i) - &X.column(j)).norm_l2())
+                    .fold(f_::INFINITY, f_::min)
+            };
+            let pos = candidates
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| nearest(**a).total_cmp(&nearest(**b)))
+                .map(|(pos, _)| pos)
+                .expect("candidates are not empty");
+
+            picked.push(candidates.remove(pos));
+        }
+
+        picked
+    }
+
+    fn archive_observations(&mut self, indices: &[usize]) {
+        let cost = self.cost();
+        let Some(mut archive) = self.archive.take() else {
+            return;
+        };
+
+        for i in indices {
+            archive.push(
+                self.x_test(self.X.column(*i)).view(),
+                self.y_raw[*i],
+                cost[*i],
+                self.fidelity_gap[*i],
+            );
+        }
+
+        self.archive = Some(archive);
+    }
+
+    /// Forgotten observations, or `None` if they are not archived.
+    pub fn archive(&self) -> Option<&Archive> {
+        self.archive.as_ref()
+    }
+
+    /// Archive together with the observations in memory other than the best, e.g. to reload into a
+    /// new memory on restart. `None` if the observations are not archived.
+    pub fn archive_with_memory(&self) -> Option<Archive> {
+        let mut archive = self.archive.clone()?;
+        let cost = self.cost();
+        let best = self.min_index();
+
+        for i in (0..self.n()).filter(|i| *i != best) {
+            archive.push(
+                self.x_test(self.X.column(i)).view(),
+                self.y_raw[i],
+                cost[i],
+                self.fidelity_gap[i],
+            );
+        }
+
+        Some(archive)
+    }
+
+    /// Replaces the archive by `archive`, e.g. to keep the archive of a previous memory on restart.
+    pub fn restore_archive(&mut self, archive: Archive) {
+        self.archive = Some(archive);
+    }
+
+    /// Appends the observations of `archive`.
+    pub fn load_archive(&mut self, archive: &Archive) {
+        for i in 0..archive.n() {
+            self.append_with_fidelity(
+                archive.X.select(Axis(1), &[i]),
+                archive.y.slice(ndarray::s![i..i + 1]).to_owned(),
+                archive.c.slice(ndarray::s![i..i + 1]).to_owned(),
+                archive.fidelity_gap[i],
+            );
+        }
+    }
+
+    /// Removes the observations at `indices`, keeping the outputs and costs in step with the inputs.
+    pub fn remove(&mut self, indices: Vec<usize>) {
+        self.y = self.y.clone().rem_at_index(indices.clone());
+        self.y_raw = self.y_raw.clone().rem_at_index(indices.clone());
+        self.ln_c = self.ln_c.clone().rem_at_index(indices.clone());
+        self.fidelity_gap = self
+            .fidelity_gap
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !indices.contains(i))
+            .map(|(_, gap)| *gap)
+            .collect();
+        self.X = self.X.clone().rem_cols(indices);
+
+        self.set_unfitted();
+    }
+
+    /// Copy of the memory with the rescaled log evaluation costs as outputs, sharing the input transform.
+    pub fn cost_memory(&self) -> Memory {
+        let mut mem = self.clone();
+        mem.y = self.ln_c.clone();
+        mem.y_raw = self.ln_c.clone();
+        mem.y_offset = 0.0;
+        mem.y_scaling = 1.0;
+        mem.warping = OutputWarping::None;
+        mem.clipping = OutlierClipping::None;
+        mem.archive = None;
+        mem.ln_c = Array1::zeros((self.n(),));
+        mem.rescale_y();
+
+        mem
+    }
+
+    /// Drops input dimension `dim`, projecting the observations onto the remaining dimensions.
+    ///
+    /// The rotation is reduced to the closest orthonormal rotation of the remaining dimensions.
+    pub fn remove_dim(&mut self, dim: usize) {
+        let keep: Vec<usize> = (0..self.X.nrows()).filter(|i| *i != dim).collect();
+
+        let X = self.X().select(Axis(0), &keep);
+        let rotate = self.X_rotate.select(Axis(0), &keep).select(Axis(1), &keep);
+        self.X_rotate = match rotate.svd(true, true) {
+            Ok((Some(u), _, Some(vt))) => u.dot(&vt),
+            _ => Array2::eye(keep.len()),
+        };
+        self.X_rotate_inv = self.X_rotate.t().to_owned();
+        self.X_scale = Array2::from_diag(&self.X_scale.diag().select(Axis(0), &keep));
+        self.X_scale_inv = Array2::from_diag(&self.X_scale.diag().map(|val| 1.0 / val));
+        self.X_offset = self.X_offset.select(Axis(0), &keep);
+        self.categorical = self
+            .categorical
+            .iter()
+            .filter(|d| **d != dim)
+            .map(|d| if *d > dim { d - 1 } else { *d })
+            .collect();
+        if let Some(archive) = self.archive.as_mut() {
+            archive.X = archive.X.select(Axis(0), &keep);
+        }
+
+        self.X = self
+            .X_scale_inv
+            .dot(&self.X_rotate_inv)
+            .dot(&X.sub_column(&self.X_offset));
+
+        self.set_unfitted();
+    }
+
+    /// Inserts input dimension `dim` with every observation at `value`, scaled by `scale` and not
+    /// rotated with the other dimensions. Categorical dimensions are left untransformed.
+    pub fn insert_dim(&mut self, dim: usize, value: f_, scale: f_, categorical: bool) {
+        let d = self.X.nrows() + 1;
+        let (offset_val, scale) = match categorical {
+            true => (0.0, 1.0),
+            false => (value, scale),
+        };
+        self.categorical
+            .iter_mut()
+            .filter(|d| **d >= dim)
+            .for_each(|d| *d += 1);
+        if categorical {
+            self.categorical.push(dim);
+        }
+
+        let src = |i: usize| if i > dim { i - 1 } else { i };
+
+        let X_true = self.X();
+        let X = Array2::from_shape_fn((d, self.n()), |(i, j)| match i == dim {
+            true => value,
+            false => X_true[(src(i), j)],
+        });
+        self.X_rotate = Array2::from_shape_fn((d, d), |(i, j)| match (i == dim, j == dim) {
+            (true, true) => 1.0,
+            (false, false) => self.X_rotate[(src(i), src(j))],
+            _ => 0.0,
+        });
+        self.X_rotate_inv = self.X_rotate.t().to_owned();
+        let scales = self.X_scale.diag().to_owned();
+        self.X_scale = Array2::from_diag(&Array1::from_shape_fn((d,), |i| match i == dim {
+            true => scale,
+            false => scales[src(i)],
+        }));
+        self.X_scale_inv = Array2::from_diag(&self.X_scale.diag().map(|val| 1.0 / val));
+        let offset = self.X_offset.clone();
+        self.X_offset = Array1::from_shape_fn((d,), |i| match i == dim {
+            true => offset_val,
+            false => offset[src(i)],
+        });
+        if let Some(archive) = self.archive.as_mut() {
+            archive.X = Array2::from_shape_fn((d, archive.n()), |(i, j)| match i == dim {
+                true => value,
+                false => archive.X[(src(i), j)],
+            });
+        }
+
+        self.X = self
+            .X_scale_inv
+            .dot(&self.X_rotate_inv)
+            .dot(&X.sub_column(&self.X_offset));
 
         self.set_unfitted();
     }
 
+    /// Offset of the origin of the local coordinates, in the space of the bounds.
+    pub fn X_offset(&self) -> &Array1<f_> {
+        &self.X_offset
+    }
+
+    /// Diagonal scaling from the local coordinates, applied before rotation.
+    pub fn X_scale(&self) -> &Array2<f_> {
+        &self.X_scale
+    }
+
+    /// Orthonormal rotation from the local coordinates, applied after scaling.
+    pub fn X_rotate(&self) -> &Array2<f_> {
+        &self.X_rotate
+    }
+
+    /// Trust region spanned by the local search domain in the space of the bounds.
+    pub fn trust_region(&self, search_dom: &ArrayBounds) -> TrustRegion {
+        let centre = self.x_test(search_dom.midpoint().view());
+        let half_lengths = self.X_scale.diag().to_owned() * search_dom.axes_len();
+
+        TrustRegion::new(centre, self.X_rotate.clone(), half_lengths)
+    }
+
     #[inline(always)]
     pub fn X(&self) -> Array2<f_> {
         // self.X_trans_inv.dot(&self.X).add_column(&self.X_offset)
@@ -236,6 +862,13 @@ impl Memory {
         self.X_rotate.dot(&self.X_scale).dot(&x_test) + &self.X_offset
     }
 
+    #[inline(always)]
+    pub fn x_prime(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        self.X_scale_inv
+            .dot(&self.X_rotate_inv)
+            .dot(&(&x - &self.X_offset))
+    }
+
     #[inline(always)]
     fn X_prime_min(&self) -> ArrayView1<f_> {
         self.X.column(self.min_index())
@@ -246,14 +879,52 @@ impl Memory {
         self.x_test(self.X_prime_min())
     }
 
+    /// Outputs as observed, without clipping or warping.
     #[inline(always)]
     pub fn y(&self) -> Array1<f_> {
-        self.y.mapv(|val| val * self.y_scaling) + self.y_offset
+        self.y_raw.clone()
+    }
+
+    #[inline(always)]
+    fn clip(&self, y: f_) -> f_ {
+        match self.y_clip {
+            Some(threshold) => y.min(threshold),
+            None => y,
+        }
+    }
+
+    /// Number of outputs in memory that are clipped as outliers.
+    pub fn n_clipped(&self) -> usize {
+        match self.y_clip {
+            Some(threshold) => self.y_raw.iter().filter(|y| **y > threshold).count(),
+            None => 0,
+        }
+    }
+
+    /// Fidelity levels below the highest fidelity of every observation, zero without a fidelity parameter.
+    #[inline(always)]
+    pub fn fidelity_gap(&self) -> &Vec<usize> {
+        &self.fidelity_gap
+    }
+
+    #[inline(always)]
+    pub fn cost(&self) -> Array1<f_> {
+        self.ln_c.mapv(f_::exp)
     }
 
     #[inline(always)]
     pub fn y_test(&self, y_test: f_) -> f_ {
-        (y_test * self.y_scaling) + self.y_offset
+        self.warp.unwarp((y_test * self.y_scaling) + self.y_offset)
+    }
+
+    /// Standard deviation `std` of a prediction with mean `y_test`, in the units of the outputs.
+    ///
+    /// Approximated by the slope of the output warping at the mean if the outputs are warped.
+    pub fn y_test_std(&self, y_test: f_, std: f_) -> f_ {
+        std * self.y_scaling
+            * self
+                .warp
+                .unwarp_slope((y_test * self.y_scaling) + self.y_offset)
     }
 
     #[inline(always)]
@@ -268,10 +939,7 @@ impl Memory {
 
     #[inline(always)]
     pub fn y_prime_min(&self) -> f_ {
-        self.y
-            .min()
-            .copied()
-            .expect("Cannot get min of empty array or array with invalid values!")
+        self.y[self.min_index()]
     }
 
     #[inline(always)]
@@ -284,7 +952,7 @@ impl Memory {
 
     #[inline(always)]
     pub fn y_min(&self) -> f_ {
-        self.y_test(self.y_prime_min())
+        self.y_raw[self.min_index()]
     }
 
     #[inline(always)]
@@ -292,12 +960,29 @@ impl Memory {
         self.y_scaling
     }
 
+    /// Range of the outputs in memory when they were last rescaled, unaffected by warping.
+    #[inline(always)]
+    pub fn y_range(&self) -> f_ {
+        self.y_range
+    }
+
+    /// Index of the best observation at the highest fidelity in memory.
     #[inline(always)]
     pub fn min_index(&self) -> usize {
-        self.y
-            .indexed_min()
-            .expect("Cannot get min of empty array or array with invalid values!")
-            .0
+        let top = self.fidelity_gap.iter().min().copied().unwrap_or(0);
+
+        let min = self
+            .y
+            .iter()
+            .zip(self.fidelity_gap.iter())
+            .enumerate()
+            .filter(|(_, (_, gap))| **gap == top)
+            .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b));
+
+        match min {
+            Some((i, (y, _))) if y.is_finite() => i,
+            _ => panic!("Cannot get min of empty array or array with invalid values!"),
+        }
     }
 
     #[inline(always)]
@@ -317,3 +1002,22 @@ impl Memory {
             .any(|col| col.abs_diff_eq(&x, 1e-12))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::array;
+
+    #[test]
+    fn mad_plateau() {
+        let clipping = OutlierClipping::Mad(3.0);
+
+        let y = array![0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.2, 50.0];
+        let threshold = clipping.threshold(&y).expect("outlier should be clipped");
+        assert!(threshold > 1.2 && threshold < 50.0, "{}", threshold);
+
+        let y = array![1.0, 1.0, 1.0, 1.0];
+        assert_eq!(clipping.threshold(&y), None);
+    }
+}
@@ -0,0 +1,314 @@
+use ndarray::Array1;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::f_;
+
+/// Monotonic transform of the outputs, fitted to the outputs in memory and applied before the
+/// outputs are rescaled and modelled.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum OutputWarping {
+    #[default]
+    None,
+    /// `ln(y - y_min + offset * (y_max - y_min))`, with the offset relative to the range of the outputs.
+    Log(f_),
+    /// Box-Cox transform of `y - y_min + 0.01 * (y_max - y_min)`, with the power fitted by maximum likelihood.
+    BoxCox,
+    /// Yeo-Johnson transform of the standardised outputs, with the power fitted by maximum likelihood.
+    YeoJohnson,
+    /// Normal quantiles of the ranks of the outputs, i.e. a Gaussian copula.
+    Copula,
+}
+
+impl OutputWarping {
+    pub fn parse_output_warping(warping: &str, param: Option<f_>) -> Option<OutputWarping> {
+        match (warping.to_lowercase().as_str(), param) {
+            ("none", _) => Some(OutputWarping::None),
+            ("log", offset) => Some(OutputWarping::Log(offset.unwrap_or(0.01))),
+            ("box_cox" | "boxcox", _) => Some(OutputWarping::BoxCox),
+            ("yeo_johnson" | "yeojohnson", _) => Some(OutputWarping::YeoJohnson),
+            ("copula" | "rank", _) => Some(OutputWarping::Copula),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum WarpFn {
+    Identity,
+    Log { shift: f_ },
+    BoxCox { shift: f_, lambda: f_ },
+    YeoJohnson { mean: f_, std: f_, lambda: f_ },
+    Copula { y: Vec<f_>, z: Vec<f_> },
+}
+
+/// [`OutputWarping`] fitted to a set of outputs, extended linearly beyond the smallest and largest
+/// fitted outputs so that it is monotonic and invertible over all values.
+#[derive(Debug, Clone)]
+pub struct OutputWarp {
+    warp_fn: WarpFn,
+    lower: (f_, f_),  // smallest fitted output and its warped value
+    upper: (f_, f_),  // largest fitted output and its warped value
+    slopes: (f_, f_), // derivatives at the smallest and largest fitted outputs
+}
+
+impl OutputWarp {
+    pub fn identity() -> OutputWarp {
+        OutputWarp {
+            warp_fn: WarpFn::Identity,
+            lower: (0.0, 0.0),
+            upper: (1.0, 1.0),
+            slopes: (1.0, 1.0),
+        }
+    }
+
+    pub fn fit(warping: &OutputWarping, y: &Array1<f_>) -> OutputWarp {
+        let min = y.iter().copied().fold(f_::INFINITY, f_::min);
+        let max = y.iter().copied().fold(f_::NEG_INFINITY, f_::max);
+        let range = max - min;
+
+        if !(range > 0.0 && range.is_finite()) {
+            return OutputWarp::identity();
+        }
+
+        let warp_fn = match warping {
+            OutputWarping::None => WarpFn::Identity,
+            OutputWarping::Log(offset) => WarpFn::Log {
+                shift: min - offset * range,
+            },
+            OutputWarping::BoxCox => {
+                let shift = min - 0.01 * range;
+                let ln_u: f_ = y.iter().map(|y| (y - shift).ln()).sum();
+                let lambda = fit_lambda(|lambda| {
+                    let z = y.mapv(|y| box_cox(y - shift, lambda));
+                    log_lik(&z) + (lambda - 1.0) * ln_u
+                });
+                WarpFn::BoxCox { shift, lambda }
+            }
+            OutputWarping::YeoJohnson => {
+                let mean = y.mean().expect("outputs are not empty");
+                let std = y.std(0.0);
+                let u = y.mapv(|y| (y - mean) / std);
+                let ln_u: f_ = u.iter().map(|u| u.signum() * (u.abs() + 1.0).ln()).sum();
+                let lambda = fit_lambda(|lambda| {
+                    let z = u.mapv(|u| yeo_johnson(u, lambda));
+                    log_lik(&z) + (lambda - 1.0) * ln_u
+                });
+                WarpFn::YeoJohnson { mean, std, lambda }
+            }
+            OutputWarping::Copula => copula(y),
+        };
+
+        let h = 1e-6 * range;
+        let lower = (min, warp_fn.warp(min));
+        let upper = (max, warp_fn.warp(max));
+        let slopes = (
+            (warp_fn.warp(min + h) - lower.1) / h,
+            (upper.1 - warp_fn.warp(max - h)) / h,
+        );
+
+        match slopes.0 > 0.0 && slopes.1 > 0.0 && slopes.0.is_finite() && slopes.1.is_finite() {
+            true => OutputWarp {
+                warp_fn,
+                lower,
+                upper,
+                slopes,
+            },
+            false => OutputWarp::identity(),
+        }
+    }
+
+    pub fn warp(&self, y: f_) -> f_ {
+        match &self.warp_fn {
+            WarpFn::Identity => y,
+            _ if y < self.lower.0 => self.lower.1 + (y - self.lower.0) * self.slopes.0,
+            _ if y > self.upper.0 => self.upper.1 + (y - self.upper.0) * self.slopes.1,
+            warp_fn => warp_fn.warp(y),
+        }
+    }
+
+    pub fn unwarp(&self, z: f_) -> f_ {
+        match &self.warp_fn {
+            WarpFn::Identity => z,
+            _ if z < self.lower.1 => self.lower.0 + (z - self.lower.1) / self.slopes.0,
+            _ if z > self.upper.1 => self.upper.0 + (z - self.upper.1) / self.slopes.1,
+            warp_fn => warp_fn.unwarp(z),
+        }
+    }
+
+    /// Derivative of the inverse warping at the warped value `z`.
+    pub fn unwarp_slope(&self, z: f_) -> f_ {
+        let h = 1e-6 * (self.upper.1 - self.lower.1).abs().max(1e-6);
+        (self.unwarp(z + h) - self.unwarp(z - h)) / (2.0 * h)
+    }
+}
+
+impl WarpFn {
+    fn warp(&self, y: f_) -> f_ {
+        match self {
+            WarpFn::Identity => y,
+            WarpFn::Log { shift } => (y - shift).ln(),
+            WarpFn::BoxCox { shift, lambda } => box_cox(y - shift, *lambda),
+            WarpFn::YeoJohnson { mean, std, lambda } => yeo_johnson((y - mean) / std, *lambda),
+            WarpFn::Copula { y: ys, z: zs } => interpolate(ys, zs, y),
+        }
+    }
+
+    fn unwarp(&self, z: f_) -> f_ {
+        match self {
+            WarpFn::Identity => z,
+            WarpFn::Log { shift } => z.exp() + shift,
+            WarpFn::BoxCox { shift, lambda } => inv_box_cox(z, *lambda) + shift,
+            WarpFn::YeoJohnson { mean, std, lambda } => inv_yeo_johnson(z, *lambda) * std + mean,
+            WarpFn::Copula { y: ys, z: zs } => interpolate(zs, ys, z),
+        }
+    }
+}
+
+/// Power maximising `log_lik` over [-2, 2].
+fn fit_lambda<F: Fn(f_) -> f_>(log_lik: F) -> f_ {
+    (0..=200)
+        .map(|i| -2.0 + 0.02 * i as f_)
+        .map(|lambda| (log_lik(lambda), lambda))
+        .filter(|(ll, _)| ll.is_finite())
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, lambda)| lambda)
+        .unwrap_or(1.0)
+}
+
+/// Profile log likelihood of normally distributed `z`, without the Jacobian of the transform.
+fn log_lik(z: &Array1<f_>) -> f_ {
+    -0.5 * z.len() as f_ * z.var(0.0).ln()
+}
+
+fn box_cox(u: f_, lambda: f_) -> f_ {
+    match lambda.abs() < 1e-6 {
+        true => u.ln(),
+        false => (u.powf(lambda) - 1.0) / lambda,
+    }
+}
+
+fn inv_box_cox(z: f_, lambda: f_) -> f_ {
+    match lambda.abs() < 1e-6 {
+        true => z.exp(),
+        false => (lambda * z + 1.0).powf(lambda.recip()),
+    }
+}
+
+fn yeo_johnson(u: f_, lambda: f_) -> f_ {
+    match u >= 0.0 {
+        true => box_cox(u + 1.0, lambda),
+        false => -box_cox(1.0 - u, 2.0 - lambda),
+    }
+}
+
+fn inv_yeo_johnson(z: f_, lambda: f_) -> f_ {
+    match z >= 0.0 {
+        true => inv_box_cox(z, lambda) - 1.0,
+        false => 1.0 - inv_box_cox(-z, 2.0 - lambda),
+    }
+}
+
+/// Normal quantiles of the ranks of the distinct outputs, averaged over tied outputs.
+#[allow(clippy::unnecessary_cast)] // statrs only supports f64
+fn copula(y: &Array1<f_>) -> WarpFn {
+    let n = y.len() as f_;
+    let normal = Normal::new(0.0, 1.0).expect("standard normal is valid");
+
+    let mut sorted = y.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let mut ys: Vec<f_> = vec![];
+    let mut zs: Vec<f_> = vec![];
+    let mut ties = 0;
+    for (rank, val) in sorted.iter().enumerate() {
+        let z = normal.inverse_cdf(((rank as f_ + 0.5) / n) as f64) as f_;
+
+        match ys.last() {
+            Some(last) if last == val => {
+                ties += 1;
+                let mean = zs.last_mut().expect("same length as ys");
+                *mean += (z - *mean) / (ties + 1) as f_;
+            }
+            _ => {
+                ties = 0;
+                ys.push(*val);
+                zs.push(z);
+            }
+        }
+    }
+
+    WarpFn::Copula { y: ys, z: zs }
+}
+
+/// Piecewise linear interpolation of the points `(xs, ys)` at `x`, with increasing `xs` and `ys`.
+fn interpolate(xs: &[f_], ys: &[f_], x: f_) -> f_ {
+    let i = xs.partition_point(|val| *val <= x).clamp(1, xs.len() - 1);
+    let t = (x - xs[i - 1]) / (xs[i] - xs[i - 1]);
+
+    ys[i - 1] + t * (ys[i] - ys[i - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::array;
+
+    /// Checks the round trip of `warping` fitted to skewed outputs, both inside and outside the
+    /// range of the fitted outputs.
+    fn check_round_trip(warping: OutputWarping) {
+        let y = array![0.5, 1.0, 1.0, 2.0, 3.5, 8.0, 20.0, 75.0, 400.0];
+        let warp = OutputWarp::fit(&warping, &y);
+
+        let outside = [-50.0, -1.0, 0.0, 0.49, 400.5, 1e3, 1e5];
+        for y in y.iter().chain(&outside) {
+            let y_rt = warp.unwarp(warp.warp(*y));
+            let tol = 1e3 * f_::EPSILON * y.abs().max(1.0);
+            assert!(
+                (y_rt - y).abs() <= tol,
+                "{:?} round trip of {} gave {}",
+                warping,
+                y,
+                y_rt
+            );
+        }
+
+        let mut sorted: Vec<f_> = y.iter().chain(&outside).copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        sorted.dedup();
+        for pair in sorted.windows(2) {
+            assert!(
+                warp.warp(pair[0]) < warp.warp(pair[1]),
+                "{:?} is not increasing",
+                warping
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_log() {
+        check_round_trip(OutputWarping::Log(0.01));
+    }
+
+    #[test]
+    fn round_trip_box_cox() {
+        check_round_trip(OutputWarping::BoxCox);
+    }
+
+    #[test]
+    fn round_trip_yeo_johnson() {
+        check_round_trip(OutputWarping::YeoJohnson);
+    }
+
+    #[test]
+    fn round_trip_copula() {
+        check_round_trip(OutputWarping::Copula);
+    }
+
+    #[test]
+    fn constant_outputs_are_not_warped() {
+        let warp = OutputWarp::fit(&OutputWarping::BoxCox, &array![2.0, 2.0, 2.0]);
+        assert_eq!(warp.warp(3.0), 3.0);
+        assert_eq!(warp.unwarp(3.0), 3.0);
+    }
+}
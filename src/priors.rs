@@ -0,0 +1,165 @@
+use ndarray::{Array1, ArrayView1};
+use rand::Rng;
+use statrs::distribution::{ContinuousCDF, Gamma, Normal};
+
+use crate::f_;
+
+/// Prior over a positive hyperparameter of the kernel.
+///
+/// Hyperparameters are optimised in log space, so the densities below are over the log of the
+/// hyperparameter, including the Jacobian of the log transform, and only up to a constant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prior {
+    /// Flat prior over the log of the hyperparameter, i.e. no prior.
+    Flat,
+    /// Normal prior with mean `mu` and standard deviation `sigma` over the log of the hyperparameter.
+    LogNormal { mu: f_, sigma: f_ },
+    /// Gamma prior with shape `shape` and rate `rate` over the hyperparameter.
+    Gamma { shape: f_, rate: f_ },
+    /// Half-Cauchy prior with scale `scale` over the hyperparameter.
+    HalfCauchy { scale: f_ },
+}
+
+impl Prior {
+    pub fn parse_prior(prior: &str, params: &[f_]) -> Option<Prior> {
+        match (prior.to_lowercase().as_str(), params) {
+            ("flat" | "none", []) => Some(Prior::Flat),
+            ("log_normal" | "lognormal", [mu, sigma]) => Some(Prior::LogNormal {
+                mu: *mu,
+                sigma: *sigma,
+            }),
+            ("gamma", [shape, rate]) => Some(Prior::Gamma {
+                shape: *shape,
+                rate: *rate,
+            }),
+            ("half_cauchy" | "halfcauchy", [scale]) => Some(Prior::HalfCauchy { scale: *scale }),
+            _ => None,
+        }
+    }
+
+    /// Panics if the parameters of the prior do not define a distribution.
+    pub fn validate(&self) {
+        match self {
+            Prior::LogNormal { sigma, .. } if *sigma <= 0.0 => {
+                panic!("Standard deviation of log-normal prior must be positive!")
+            }
+            Prior::Gamma { shape, rate } if *shape <= 0.0 || *rate <= 0.0 => {
+                panic!("Shape and rate of gamma prior must be positive!")
+            }
+            Prior::HalfCauchy { scale } if *scale <= 0.0 => {
+                panic!("Scale of half-Cauchy prior must be positive!")
+            }
+            _ => (),
+        }
+    }
+
+    /// Unnormalised log density at the log hyperparameter `u`.
+    pub fn ln_pdf(&self, u: f_) -> f_ {
+        match self {
+            Prior::Flat => 0.0,
+            Prior::LogNormal { mu, sigma } => -0.5 * ((u - mu) / sigma).powi(2),
+            Prior::Gamma { shape, rate } => shape * u - rate * u.exp(),
+            Prior::HalfCauchy { scale } => u - (1.0 + (u.exp() / scale).powi(2)).ln(),
+        }
+    }
+
+    /// First derivative of [`Prior::ln_pdf`] with respect to the log hyperparameter `u`.
+    pub fn ln_pdf_grad(&self, u: f_) -> f_ {
+        match self {
+            Prior::Flat => 0.0,
+            Prior::LogNormal { mu, sigma } => -(u - mu) / sigma.powi(2),
+            Prior::Gamma { shape, rate } => shape - rate * u.exp(),
+            Prior::HalfCauchy { scale } => {
+                let r = (u.exp() / scale).powi(2);
+                1.0 - 2.0 * r / (1.0 + r)
+            }
+        }
+    }
+
+    /// Random log hyperparameter drawn from the prior by inverse transform sampling, or `None`
+    /// for the improper flat prior.
+    #[allow(clippy::unnecessary_cast)] // statrs only supports f64
+    pub fn sample(&self) -> Option<f_> {
+        let p = rand::rng().random_range(f64::EPSILON..1.0);
+
+        let u = match self {
+            Prior::Flat => None,
+            Prior::LogNormal { mu, sigma } => Normal::new(*mu as f64, *sigma as f64)
+                .ok()
+                .map(|d| d.inverse_cdf(p) as f_),
+            Prior::Gamma { shape, rate } => Gamma::new(*shape as f64, *rate as f64)
+                .ok()
+                .map(|d| (d.inverse_cdf(p) as f_).ln()),
+            Prior::HalfCauchy { scale } => {
+                Some((scale * (std::f64::consts::FRAC_PI_2 * p).tan() as f_).ln())
+            }
+        };
+
+        u.filter(|u| u.is_finite())
+    }
+
+    /// Second derivative of [`Prior::ln_pdf`] with respect to the log hyperparameter `u`.
+    pub fn ln_pdf_hess(&self, u: f_) -> f_ {
+        match self {
+            Prior::Flat => 0.0,
+            Prior::LogNormal { sigma, .. } => -sigma.powi(2).recip(),
+            Prior::Gamma { rate, .. } => -rate * u.exp(),
+            Prior::HalfCauchy { scale } => {
+                let r = (u.exp() / scale).powi(2);
+                -4.0 * r / (1.0 + r).powi(2)
+            }
+        }
+    }
+}
+
+/// Independent priors over the length scales of the kernel, one per dimension.
+///
+/// Only the length scales take priors: `sigma_f` is set from the spread of the outputs and
+/// `sigma_n` is fixed, see [`crate::hyp_opt::ThetaOptimizer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperPriors {
+    pub l: Vec<Prior>,
+}
+
+impl HyperPriors {
+    /// Priors with the same prior `l` over every length scale.
+    pub fn new(d: usize, l: Prior) -> HyperPriors {
+        HyperPriors { l: vec![l; d] }
+    }
+
+    /// Unnormalised log density of the log length scales `ln_l`.
+    pub fn ln_pdf(&self, ln_l: ArrayView1<f_>) -> f_ {
+        self.l
+            .iter()
+            .zip(ln_l.iter())
+            .map(|(prior, u)| prior.ln_pdf(*u))
+            .sum()
+    }
+
+    /// Gradient of [`HyperPriors::ln_pdf`] with respect to the log length scales.
+    pub fn ln_pdf_grad(&self, ln_l: ArrayView1<f_>) -> Array1<f_> {
+        self.l
+            .iter()
+            .zip(ln_l.iter())
+            .map(|(prior, u)| prior.ln_pdf_grad(*u))
+            .collect()
+    }
+
+    /// Diagonal of the Hessian of [`HyperPriors::ln_pdf`] with respect to the log length scales,
+    /// which are independent.
+    pub fn ln_pdf_hess(&self, ln_l: ArrayView1<f_>) -> Array1<f_> {
+        self.l
+            .iter()
+            .zip(ln_l.iter())
+            .map(|(prior, u)| prior.ln_pdf_hess(*u))
+            .collect()
+    }
+
+    pub fn remove_dim(&mut self, dim: usize) {
+        self.l.remove(dim);
+    }
+
+    pub fn insert_dim(&mut self, dim: usize, prior: Prior) {
+        self.l.insert(dim, prior);
+    }
+}
@@ -9,14 +9,20 @@ use numpy::IntoPyArray;
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 
-use crate::acq::ExpectedImprovement;
-use crate::bounds::{Bounds, Ready};
+use crate::acq::{Acquisition, ExpectedImprovement};
+use crate::bound_types::BoundTrait;
+use crate::bounds::{BoundReprs, Bounds, Ready};
+use crate::bounds_array::InitialDesign;
 use crate::gp::GP;
-use crate::hyp_opt::HyperparameterOptimizer;
+use crate::hyp_opt::{HyperparameterOptimizer, ThetaOptimizer};
 use crate::kernel::Kernel;
+use crate::memory::{Archiving, ForgetStrategy, OutlierClipping, PCAWeighting};
+use crate::output_warping::OutputWarping;
+use crate::priors::{HyperPriors, Prior};
+use crate::search_space::SearchSpace;
 use crate::utils::Array1Utils;
 use crate::{Auto, Config, GPState, LABCAT, LABCATReadyState, Manual, f_};
-use crate::{LABCATConfig, OptimizationSummary};
+use crate::{LABCATConfig, OptimizationSummary, TermCond};
 
 #[cfg(feature = "python")]
 #[derive(Clone)]
@@ -35,16 +41,33 @@ impl pyConfig {
         }
     }
 
-    pub fn target_fn<'py>(&self, x: &Array2<f_>, py: Python<'py>) -> Array1<f_> {
+    /// Evaluates the python target function, which may return either the objective function values
+    /// or a tuple of the objective function values and the evaluation costs. The fidelity level is
+    /// passed as a second argument if the bounds have a fidelity parameter.
+    pub fn target_fn<'py>(
+        &self,
+        x: &Array2<f_>,
+        fidelity: Option<usize>,
+        py: Python<'py>,
+    ) -> (Array1<f_>, Option<Array1<f_>>) {
         let x = x.to_pyarray(py);
 
-        match &self.py_callable_target_fn {
-            Some(f) => match f.call1(py, (x,)) {
+        let ret = match (&self.py_callable_target_fn, fidelity) {
+            (Some(f), Some(fidelity)) => Some(f.call1(py, (x, fidelity))),
+            (Some(f), None) => Some(f.call1(py, (x,))),
+            (None, _) => None,
+        };
+
+        match ret {
+            Some(ret) => match ret {
                 Ok(ret) => match ret.extract::<Vec<f_>>(py) {
-                    Ok(vec) => Array1::from_vec(vec),
-                    Err(_) => panic!(
-                        "Python target function must return a value that can be parsed to vector!"
-                    ),
+                    Ok(vec) => (Array1::from_vec(vec), None),
+                    Err(_) => match ret.extract::<(Vec<f_>, Vec<f_>)>(py) {
+                        Ok((y, c)) => (Array1::from_vec(y), Some(Array1::from_vec(c))),
+                        Err(_) => panic!(
+                            "Python target function must return a value that can be parsed to vector, or a tuple of vectors!"
+                        ),
+                    },
                 },
                 Err(_) => panic!("Failed to call python target function!"),
             },
@@ -86,15 +109,26 @@ impl pyConfig {
 #[cfg(feature = "python")]
 impl LABCAT {
     pub fn new(bounds: Bounds<Ready>) -> LABCAT<Config> {
-        let gp = GP::new(bounds.bounds_arr().to_owned(), 0.5, 0.15);
+        let gp = GP::new(
+            bounds.bounds_arr().to_owned(),
+            0.5,
+            HyperPriors::new(
+                bounds.dim(),
+                Prior::LogNormal {
+                    mu: 0.0,
+                    sigma: 0.15,
+                },
+            ),
+        )
+        .with_fidelity(bounds.fidelity())
+        .with_categorical(bounds.categorical_dims());
+        let config = LABCATConfig::new();
 
-        #[cfg(feature = "LHS")]
-        let init_points = bounds.bounds_arr().LHS_sample(bounds.dim() + 1);
-        #[cfg(not(feature = "LHS"))]
-        let init_points = bounds.bounds_arr().random_sample(bounds.dim() + 1);
+        let init_points = bounds
+            .bounds_arr()
+            .sample(bounds.dim() + 1, &config.initial_design);
 
         let gp_state = GPState::Init(init_points);
-        let config = LABCATConfig::new();
         let py_config = pyConfig::new();
 
         LABCAT {
@@ -104,6 +138,7 @@ impl LABCAT {
             config,
             py_config,
             config_state: PhantomData,
+            recorder: None,
         }
     }
 
@@ -115,15 +150,18 @@ impl LABCAT {
     ) -> LABCAT<Manual> {
         let gp = GP::new(
             bounds.bounds_arr().to_owned(),
-            config.beta.into(),
-            config.prior_sigma.into(),
-        );
+            config.beta,
+            config.hyper_priors(bounds.dim()),
+        )
+        .with_fidelity(bounds.fidelity())
+        .with_categorical(bounds.categorical_dims())
+        .with_output_warping(config.output_warping.clone())
+        .with_outlier_clipping(config.outlier_clipping.clone())
+        .with_forget_strategy(config.forget_strategy.clone())
+        .with_archive(config.archive_forgotten != Archiving::Off);
         let init_n = py_config.init_pts_fn(bounds.dim(), py);
 
-        #[cfg(feature = "LHS")]
-        let init_points = bounds.bounds_arr().LHS_sample(init_n);
-        #[cfg(not(feature = "LHS"))]
-        let init_points = bounds.bounds_arr().random_sample(init_n);
+        let init_points = bounds.bounds_arr().sample(init_n, &config.initial_design);
 
         let gp_state = GPState::Init(init_points);
 
@@ -134,6 +172,7 @@ impl LABCAT {
             config,
             py_config,
             config_state: PhantomData,
+            recorder: None,
         }
     }
 
@@ -160,6 +199,33 @@ impl LABCAT<Config> {
         self.config.prior_sigma = sigma;
     }
 
+    pub fn length_scale_prior(&mut self, prior: Prior) {
+        prior.validate();
+        self.config.l_prior = Some(prior);
+    }
+
+    pub fn length_scale_prior_of(&mut self, label: &str, prior: Prior) {
+        let dim = self
+            .bounds
+            .iter_bounds()
+            .position(|bound| bound.label() == label)
+            .unwrap_or_else(|| panic!("Bound {} could not be found!", label));
+
+        self.config.set_l_prior(dim, prior);
+    }
+
+    pub fn rescale_cap(&mut self, cap: Option<f_>) {
+        self.config.set_rescale_cap(cap);
+    }
+
+    pub fn theta_optimizer(&mut self, optimizer: ThetaOptimizer) {
+        self.config.set_theta_optimizer(optimizer);
+    }
+
+    pub fn theta_sampling(&mut self, n_samples: usize, burn_in: usize, thin: usize) {
+        self.config.set_theta_sampling(n_samples, burn_in, thin);
+    }
+
     pub fn restarts(&mut self, restarts: bool) {
         self.config.restarts = restarts;
     }
@@ -172,19 +238,101 @@ impl LABCAT<Config> {
         self.py_config.py_callable_forget_fn = Some(f);
     }
 
+    pub fn initial_design(&mut self, design: InitialDesign) {
+        self.config.initial_design = design;
+    }
+
+    pub fn rotation(&mut self, rotation: bool) {
+        self.config.rotation = rotation;
+    }
+
+    pub fn pca_weighting(&mut self, weighting: PCAWeighting) {
+        match weighting {
+            PCAWeighting::Softmin(temp) if temp <= 0.0 || !temp.is_finite() => {
+                panic!("Temperature of softmin PCA weighting must be positive!")
+            }
+            PCAWeighting::TopK(0) => {
+                panic!("Number of observations of top-k PCA weighting must be at least 1!")
+            }
+            _ => (),
+        }
+
+        self.config.pca_weighting = weighting;
+    }
+
+    pub fn acquisition(&mut self, acquisition: Acquisition) {
+        self.config.acquisition = acquisition;
+    }
+
+    pub fn output_warping(&mut self, warping: OutputWarping) {
+        if let OutputWarping::Log(offset) = warping
+            && offset <= 0.0
+        {
+            panic!("Offset of log output warping must be positive!")
+        }
+
+        self.config.output_warping = warping;
+    }
+
+    pub fn outlier_clipping(&mut self, clipping: OutlierClipping) {
+        match clipping {
+            OutlierClipping::Mad(k) if k <= 0.0 => {
+                panic!("Number of deviations of outlier clipping must be positive!")
+            }
+            OutlierClipping::Quantile(q) if q <= 0.5 || q > 1.0 => {
+                panic!("Quantile of outlier clipping must be in the interval (0.5, 1]!")
+            }
+            _ => (),
+        }
+
+        self.config.outlier_clipping = clipping;
+    }
+
+    pub fn forget_strategy(&mut self, strategy: ForgetStrategy) {
+        self.config.forget_strategy = strategy;
+    }
+
+    pub fn archive_forgotten(&mut self, archiving: Archiving) {
+        self.config.archive_forgotten = archiving;
+    }
+
+    pub fn min_trust_region(&mut self, len: f_) {
+        self.config
+            .set_trust_region_lens(Some(len), self.config.max_tr_len);
+    }
+
+    pub fn max_trust_region(&mut self, len: f_) {
+        self.config
+            .set_trust_region_lens(self.config.min_tr_len, Some(len));
+    }
+
+    pub fn rotation_smoothing(&mut self, alpha: f_) {
+        if alpha <= 0.0 || alpha > 1.0 {
+            panic!("Rotation smoothing factor must be in the interval (0, 1]!")
+        }
+
+        self.config.rotation_smoothing = Some(alpha);
+    }
+
     pub fn build<'py>(self, py: Python<'py>) -> LABCAT<Manual> {
         let gp = GP::new(
             self.bounds.bounds_arr().to_owned(),
-            self.config.beta.into(),
-            self.config.prior_sigma.into(),
-        );
+            self.config.beta,
+            self.config.hyper_priors(self.bounds.dim()),
+        )
+        .with_fidelity(self.bounds.fidelity())
+        .with_categorical(self.bounds.categorical_dims())
+        .with_output_warping(self.config.output_warping.clone())
+        .with_outlier_clipping(self.config.outlier_clipping.clone())
+        .with_forget_strategy(self.config.forget_strategy.clone())
+        .with_archive(self.config.archive_forgotten != Archiving::Off);
 
         let init_n = self.py_config.init_pts_fn(self.bounds.dim(), py);
 
-        #[cfg(feature = "LHS")]
-        let init_points = self.bounds.bounds_arr().LHS_sample(init_n);
-        #[cfg(not(feature = "LHS"))]
-        let init_points = self.bounds.bounds_arr().random_sample(init_n);
+        let init_points = self
+            .bounds
+            .bounds_arr()
+            .sample(init_n, &self.config.initial_design);
 
         let gp_state = GPState::Init(init_points);
 
@@ -195,6 +343,7 @@ impl LABCAT<Config> {
             config: self.config,
             py_config: self.py_config,
             config_state: PhantomData,
+            recorder: self.recorder,
         }
     }
 }
@@ -203,32 +352,55 @@ impl LABCAT<Config> {
 impl<S: LABCATReadyState> LABCAT<S> {
     fn restart<'py>(&mut self, _err: anyhow::Error, py: Python<'py>) {
         let mut gp = GP::new(
-            self.bounds.bounds_arr().to_owned(),
-            self.config.beta.into(),
-            self.config.prior_sigma.into(),
-        );
+            self.gp.bounds.clone(),
+            self.config.beta,
+            self.gp.priors.clone(),
+        )
+        .with_fidelity(self.bounds.fidelity())
+        .with_categorical(self.gp.categorical.clone())
+        .with_output_warping(self.config.output_warping.clone())
+        .with_outlier_clipping(self.config.outlier_clipping.clone())
+        .with_forget_strategy(self.config.forget_strategy.clone())
+        .with_archive(self.config.archive_forgotten != Archiving::Off);
 
         let init_n = self.py_config.init_pts_fn(self.bounds.dim(), py);
 
-        #[cfg(feature = "LHS")]
-        let init_points = self.bounds.bounds_arr().LHS_sample(init_n - 1);
-        #[cfg(not(feature = "LHS"))]
-        let init_points = self.bounds.bounds_arr().random_sample(init_n - 1);
+        let init_points = self
+            .gp
+            .bounds
+            .sample(init_n - 1, &self.config.initial_design);
 
         let gp_state = GPState::Init(init_points);
 
-        gp.mem.append(
+        let min_index = self.gp.mem.min_index();
+        gp.mem.append_with_fidelity(
             self.gp.mem.X_min().into_col(),
             Array1::from_elem((1,), self.gp.mem.y_min()),
+            Array1::from_elem((1,), self.gp.mem.cost()[min_index]),
+            self.gp.mem.fidelity_gap()[min_index],
         );
+        if let Some(archive) = self.gp.mem.archive_with_memory() {
+            match self.config.archive_forgotten {
+                Archiving::ReloadOnRestart => gp.mem.load_archive(&archive),
+                _ => gp.mem.restore_archive(archive),
+            }
+        }
         self.gp = gp;
         self.gp_state = gp_state;
+        self.config.last_improvement = Some((self.config.n_samples, self.gp.mem.y_min()));
     }
 
     fn _suggest<'py>(&mut self, py: Python<'py>) -> Array2<f_> {
+        self.config.next_fidelity = None;
         self.state_transition();
         match self.gp_state.clone() {
-            GPState::Init(init_pts) => init_pts,
+            GPState::Init(init_pts) => {
+                let mut X = self.expand_X(self.apply_context(init_pts));
+                X.columns_mut()
+                    .into_iter()
+                    .for_each(|mut col| col.assign(&self.bounds.snap(col.view())));
+                X
+            }
             GPState::Nominal => match self.step_alogrithm(py) {
                 Ok(Arr) => Arr,
                 Err(err) => {
@@ -240,35 +412,49 @@ impl<S: LABCATReadyState> LABCAT<S> {
     }
 
     fn step_alogrithm<'py>(&mut self, py: Python<'py>) -> Result<Array2<f_>> {
-        let min = self.gp.mem.X.column(self.gp.mem.min_index()).to_owned();
-        self.gp.mem.recenter_X(min.view());
+        let centre = self.centre()?;
+        self.gp.mem.recenter_X(centre.view());
 
         self.gp.mem.rescale_y();
 
-        #[cfg(feature = "PCA")]
-        self.gp.mem.rotate_X()?;
+        if self.config.rotation {
+            self.gp
+                .mem
+                .rotate_X(&self.config.pca_weighting, self.config.rotation_smoothing)?;
+        }
 
         self.gp.fit()?;
 
-        match self.gp.optimize_thetas() {
-            Ok(_) => assert!(true),
+        match self.optimize_thetas() {
+            Ok(log_lik) => self.config.log_lik = Some(log_lik),
             Err(_) => {
+                self.config.log_lik = None;
                 self.gp.kernel.whiten_l();
                 self.gp.fit()?;
             }
         };
 
+        let thetas = self.sample_thetas()?;
+        let X_scale = self.gp.mem.X_scale().diag().to_owned();
+
         self.gp
             .mem
-            .rescale_X(self.gp.kernel.l(), Some(self.config.prior_sigma.into()));
+            .rescale_X(self.gp.kernel.l(), self.config.rescale_cap);
         self.gp.kernel.whiten_l();
 
+        let (min_len, max_len) = self.trust_region_lens();
+        self.gp
+            .mem
+            .clamp_X_scale(&self.gp.search_dom, min_len.as_ref(), max_len.as_ref());
+        let thetas = self.rescale_thetas(thetas, &X_scale);
+
         let min_n = self.py_config.forget_fn(self.bounds.dim(), py);
-        self.gp.mem.forget(&self.gp.search_dom, min_n);
+        self.forget(min_n);
 
         self.gp.fit()?;
-        let (_, ei_pt) = self.gp.optimize_ei(10 * self.bounds.dim())?;
-        let scaled_ei_pt = self.gp.mem.x_test(ei_pt.view()).into_col();
+        let ei_pt = self.acquire(&thetas)?;
+        self.config.next_fidelity = self.select_fidelity(ei_pt.view());
+        let scaled_ei_pt = self.suggestion(ei_pt.view()).into_col();
         Ok(scaled_ei_pt)
     }
 }
@@ -289,21 +475,91 @@ impl LABCAT<Manual> {
             config: self.config,
             py_config,
             config_state: PhantomData,
+            recorder: self.recorder,
         }
     }
 
     pub fn suggest<'py>(&mut self, py: Python<'py>) -> Array2<f_> {
-        self._suggest(py)
+        let suggestion = self._suggest(py);
+        self.record_frame(&suggestion);
+        suggestion
+    }
+
+    /// Suggested points in the units of the bounds, leaving out inactive parameters.
+    pub fn suggest_labelled<'py>(&mut self, py: Python<'py>) -> Vec<BoundReprs> {
+        let suggestion = self.suggest(py);
+        self.reprs(&suggestion)
+    }
+
+    /// Suggested points as structs of the search space `T`, which should match the bounds.
+    pub fn suggest_typed<'py, T: SearchSpace>(&mut self, py: Python<'py>) -> Vec<T> {
+        self.suggest_labelled(py).into_iter().map(T::from).collect()
+    }
+
+    pub fn suggest_with_context<'py>(
+        &mut self,
+        context: Array1<f_>,
+        py: Python<'py>,
+    ) -> Array2<f_> {
+        if context.len() != self.bounds.context_dims().len() {
+            panic!("Length of context does not match the number of context variables!")
+        }
+
+        self.config.context = Some(context);
+        let suggestion = self.suggest(py);
+        self.config.context = None;
+
+        suggestion
+    }
+
+    pub fn suggest_with_fidelity<'py>(&mut self, py: Python<'py>) -> (Array2<f_>, usize) {
+        let suggestion = self.suggest(py);
+        let fidelity = self
+            .suggested_fidelity()
+            .expect("Bounds have no fidelity parameter!");
+
+        (suggestion, fidelity)
+    }
+
+    pub fn fix_parameter(&mut self, label: &str, value: f_) {
+        self._fix_parameter(label, value)
+    }
+
+    pub fn unfix_parameter(&mut self, label: &str) {
+        self._unfix_parameter(label)
     }
 
     pub fn observe(&mut self, X: Array2<f_>, y: Array1<f_>) {
-        self._observe(X, y)
+        self._observe(X, y, None, None)
+    }
+
+    /// Observations of points in the units of the bounds, e.g. from [`LABCAT::suggest_labelled`].
+    pub fn observe_labelled(&mut self, X: Vec<BoundReprs>, y: Array1<f_>) {
+        let X = self.parse_reprs(X);
+        self._observe(X, y, None, None)
+    }
+
+    /// Observations of points as structs of the search space `T`, e.g. from [`LABCAT::suggest_typed`].
+    pub fn observe_typed<T: SearchSpace>(&mut self, X: Vec<T>, y: Array1<f_>) {
+        self.observe_labelled(X.into_iter().map(Into::into).collect(), y)
+    }
+
+    pub fn observe_with_cost(&mut self, X: Array2<f_>, y: Array1<f_>, c: Array1<f_>) {
+        self._observe(X, y, Some(c), None)
+    }
+
+    pub fn observe_with_fidelity(&mut self, X: Array2<f_>, y: Array1<f_>, fidelity: usize) {
+        self._observe(X, y, None, Some(fidelity))
     }
 
     pub fn thetas(&self) -> &Array1<f_> {
         self.gp.kernel.thetas()
     }
 
+    pub fn log_marginal_likelihood(&self) -> Option<f_> {
+        self.config.log_lik
+    }
+
     pub fn predict(&self, mut x_prime: Array2<f_>) -> (Array2<f_>, Array2<f_>) {
         let y_prime = match self.gp.predict(x_prime.clone()) {
             Ok(res) => res.0,
@@ -319,11 +575,12 @@ impl LABCAT<Manual> {
     }
 
     pub fn observations(&self) -> (Array2<f_>, Array1<f_>) {
-        (self.gp.mem.X(), self.gp.mem.y())
+        (self.expand_X(self.gp.mem.X()), self.gp.mem.y())
     }
 
-    pub fn bounds(&self) -> &Bounds<Ready> {
-        &self.bounds
+    pub fn archived_observations(&self) -> Option<(Array2<f_>, Array1<f_>)> {
+        let archive = self.gp.mem.archive()?;
+        Some((self.expand_X(archive.X.clone()), archive.y.clone()))
     }
 
     pub fn config(&self) -> &LABCATConfig {
@@ -338,7 +595,7 @@ impl LABCAT<Manual> {
 #[cfg(feature = "python")]
 impl LABCAT<Auto> {
     pub fn target_tol(&mut self, tol: f_) {
-        self.config.target_tol = tol.into();
+        self.config.target_tol = tol;
     }
 
     pub fn target_val(&mut self, val: f_) {
@@ -357,6 +614,18 @@ impl LABCAT<Auto> {
         self.config.auto_print = Some(interval);
     }
 
+    pub fn x_tol(&mut self, tol: f_) {
+        self.config.x_tol = Some(Array1::from_elem((self.bounds.dim(),), tol));
+    }
+
+    pub fn max_stagnation(&mut self, n: usize, rel_improvement: f_) {
+        self.config.max_stagnation = Some((n, rel_improvement));
+    }
+
+    pub fn max_cost(&mut self, budget: f_) {
+        self.config.max_cost = Some(budget);
+    }
+
     pub fn run<'py>(&mut self, py: Python<'py>) -> OptimizationSummary {
         let print = self.config.auto_print.is_some();
         if print {
@@ -365,8 +634,10 @@ impl LABCAT<Auto> {
         }
         loop {
             let suggest = self._suggest(py);
-            let samples = self.py_config.target_fn(&suggest, py);
-            self._observe(suggest, samples);
+            self.record_frame(&suggest);
+            let fidelity = self.suggested_fidelity();
+            let (samples, cost) = self.py_config.target_fn(&suggest, fidelity, py);
+            self._observe(suggest, samples, cost, fidelity);
 
             if print {
                 if self.config.n_samples % self.config.auto_print.expect("Already checked option")
@@ -377,6 +648,18 @@ impl LABCAT<Auto> {
             }
 
             if let Some(term) = self._check_converged() {
+                if self.config.restarts
+                    && !matches!(
+                        term,
+                        TermCond::MaxItersReached
+                            | TermCond::MaxTimeReached
+                            | TermCond::MaxCostReached
+                    )
+                {
+                    self.restart(anyhow::format_err!("Restart after converge!"), py);
+                    continue;
+                }
+
                 if print {
                     println!("{}", self.bottom_border());
                 }
@@ -384,8 +667,10 @@ impl LABCAT<Auto> {
                 return OptimizationSummary {
                     term_reason: term,
                     n_samples: self.config.n_samples,
-                    min_x: self.gp.mem.X().column(self.gp.mem.min_index()).to_owned(),
+                    min_x: self.X_min(),
                     min_y: self.gp.mem.y()[self.gp.mem.min_index()],
+                    total_cost: self.config.total_cost,
+                    recording: self.recorder.take(),
                 };
             };
         }
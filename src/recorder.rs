@@ -0,0 +1,468 @@
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+
+use crate::{
+    bound_types::{BoundRepr, BoundTrait, BoundType},
+    bounds::{Bounds, Ready},
+    f_,
+    trust_region::TrustRegion,
+};
+
+/// GP mean and standard deviation on a regular grid over 2D bounds, indexed as `[x2 index, x1 index]`.
+/// The grid is regular in the transformed space of the bounds, `x1` and `x2` are in their original units.
+#[derive(Debug, Clone)]
+pub struct GridPrediction {
+    pub x1: Array1<f_>,
+    pub x2: Array1<f_>,
+    pub mean: Array2<f_>,
+    pub std: Array2<f_>,
+}
+
+/// Trust region of a frame in the original units of the bounds.
+#[derive(Debug, Clone)]
+pub struct FrameRegion {
+    pub centre: Array1<f_>,
+    /// Extent along each coordinate axis, see [`TrustRegion::extent_untransformed`].
+    pub extent: Array1<f_>,
+    /// Vertices as columns, see [`TrustRegion::corners`].
+    pub polygon: Array2<f_>,
+}
+
+/// State of the algorithm captured when a suggestion is made.
+///
+/// Everything is in the original units of the bounds, except for `trust_region_transformed`.
+/// Categorical and boolean bounds keep their codes, which are written as labels to files.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub n_samples: usize,
+    pub X: Array2<f_>,
+    pub y: Array1<f_>,
+    pub trust_region: Option<FrameRegion>,
+    pub trust_region_transformed: Option<TrustRegion>,
+    pub suggestion: Array2<f_>,
+    pub grid: Option<GridPrediction>,
+}
+
+/// Records the trajectory of a run for later visualisation.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    frames: Vec<Frame>,
+    bounds: Option<Bounds<Ready>>,
+    grid_resolution: Option<usize>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder {
+            frames: vec![],
+            bounds: None,
+            grid_resolution: None,
+        }
+    }
+
+    /// Also record the GP mean and standard deviation on a `resolution x resolution` grid, 2D bounds only.
+    pub fn grid(mut self, resolution: usize) -> Recorder {
+        if resolution < 2 {
+            panic!("Grid resolution must be at least 2!")
+        }
+
+        self.grid_resolution = Some(resolution);
+        self
+    }
+
+    pub fn set_bounds(&mut self, bounds: Bounds<Ready>) {
+        self.bounds = Some(bounds);
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    /// Grid points over 2D bounds as columns in transformed space, ordered with x1 varying fastest.
+    pub fn grid_points(&self) -> Option<(Array1<f_>, Array1<f_>, Array2<f_>)> {
+        let res = self.grid_resolution?;
+        let bounds = self.bounds.as_ref()?;
+        if bounds.dim() != 2 {
+            return None;
+        }
+
+        let b = bounds.bounds_arr().bounds_arr();
+        let x1 = Array1::linspace(b[(0, 0)], b[(0, 1)], res);
+        let x2 = Array1::linspace(b[(1, 0)], b[(1, 1)], res);
+        let pts = Array2::from_shape_fn((2, res * res), |(i, j)| match i {
+            0 => x1[j % res],
+            _ => x2[j / res],
+        });
+
+        Some((x1, x2, pts))
+    }
+
+    /// Writes all frames to a single CSV file in long format, with columns
+    /// `frame,n_samples,kind,index,x1..xd,value`, in the original units of the bounds.
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        let d = self.dim();
+
+        let x_header: Vec<String> = (1..=d).map(|i| format!("x{}", i)).collect();
+        writeln!(
+            file,
+            "frame,n_samples,kind,index,{},value",
+            x_header.join(",")
+        )?;
+
+        let mut write_row = |frame: usize,
+                             n: usize,
+                             kind: &str,
+                             index: usize,
+                             x: ArrayView1<f_>,
+                             val: Option<f_>|
+         -> io::Result<()> {
+            let x = self.coords(x, false);
+            let val = val.map(|v| v.to_string()).unwrap_or_default();
+            writeln!(
+                file,
+                "{},{},{},{},{},{}",
+                frame,
+                n,
+                kind,
+                index,
+                x.join(","),
+                val
+            )
+        };
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            let n = frame.n_samples;
+
+            for (j, (x, y)) in frame
+                .X
+                .columns()
+                .into_iter()
+                .zip(frame.y.iter())
+                .enumerate()
+            {
+                write_row(i, n, "observation", j, x, Some(*y))?;
+            }
+
+            for (j, x) in frame.suggestion.columns().into_iter().enumerate() {
+                write_row(i, n, "suggestion", j, x, None)?;
+            }
+
+            if let Some(tr) = &frame.trust_region {
+                for (j, x) in tr.polygon.columns().into_iter().enumerate() {
+                    write_row(i, n, "trust_region", j, x, None)?;
+                }
+            }
+
+            if let Some(grid) = &frame.grid {
+                for ((r, c), mean) in grid.mean.indexed_iter() {
+                    let x = ndarray::arr1(&[grid.x1[c], grid.x2[r]]);
+                    let index = r * grid.x1.len() + c;
+                    write_row(i, n, "mean", index, x.view(), Some(*mean))?;
+                    write_row(i, n, "std", index, x.view(), Some(grid.std[(r, c)]))?;
+                }
+            }
+        }
+
+        file.flush()
+    }
+
+    /// Writes all frames to a JSON file as a list of frame objects, see [`Frame`] for their units.
+    pub fn write_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut json = String::from("[");
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            json.push_str("\n  {");
+            let _ = write!(json, "\"n_samples\": {}", frame.n_samples);
+            let _ = write!(json, ", \"X\": {}", self.json_points(frame.X.view()));
+            let _ = write!(json, ", \"y\": {}", json_array(frame.y.view()));
+            let _ = write!(
+                json,
+                ", \"suggestion\": {}",
+                self.json_points(frame.suggestion.view())
+            );
+
+            match &frame.trust_region {
+                Some(tr) => {
+                    let _ = write!(
+                        json,
+                        ", \"trust_region\": {{\"centre\": {}, \"extent\": {}, \"polygon\": {}}}",
+                        self.json_point(tr.centre.view()),
+                        json_array(tr.extent.view()),
+                        self.json_points(tr.polygon.view())
+                    );
+                }
+                None => json.push_str(", \"trust_region\": null"),
+            }
+
+            match &frame.trust_region_transformed {
+                Some(tr) => {
+                    let _ = write!(
+                        json,
+                        ", \"trust_region_transformed\": {{\"centre\": {}, \"axes\": {}, \"side_lengths\": {}}}",
+                        json_array(tr.centre().view()),
+                        json_columns(tr.axes().view()),
+                        json_array(tr.side_lengths().view())
+                    );
+                }
+                None => json.push_str(", \"trust_region_transformed\": null"),
+            }
+
+            match &frame.grid {
+                Some(grid) => {
+                    let _ = write!(
+                        json,
+                        ", \"grid\": {{\"x1\": {}, \"x2\": {}, \"mean\": {}, \"std\": {}}}",
+                        json_array(grid.x1.view()),
+                        json_array(grid.x2.view()),
+                        json_rows(grid.mean.view()),
+                        json_rows(grid.std.view())
+                    );
+                }
+                None => json.push_str(", \"grid\": null"),
+            }
+
+            json.push('}');
+        }
+
+        json.push_str("\n]\n");
+        fs::write(path, json)
+    }
+
+    /// Writes each frame of a 2D run to `frame_XXXX.svg` in the given directory.
+    pub fn write_svg_frames(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let bounds = match &self.bounds {
+            Some(bounds) if bounds.dim() == 2 => {
+                let b = bounds.bounds_arr().bounds_arr();
+                let lower = bounds.untransform(b.column(0));
+                let upper = bounds.untransform(b.column(1));
+                Array2::from_shape_fn((2, 2), |(i, j)| match j {
+                    0 => lower[i].min(upper[i]),
+                    _ => lower[i].max(upper[i]),
+                })
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "SVG frames can only be written for 2D bounds!",
+                ));
+            }
+        };
+
+        fs::create_dir_all(&dir)?;
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            let svg = svg_frame(frame, &bounds);
+            fs::write(dir.as_ref().join(format!("frame_{:04}.svg", i)), svg)?;
+        }
+
+        Ok(())
+    }
+
+    /// Coordinates of `x` as written to files, with the labels of categorical and boolean bounds in
+    /// place of their codes.
+    fn coords(&self, x: ArrayView1<f_>, json: bool) -> Vec<String> {
+        let num = |v: &f_| match json && !v.is_finite() {
+            true => "null".into(),
+            false => v.to_string(),
+        };
+
+        match &self.bounds {
+            Some(bounds) if bounds.dim() == x.len() => bounds
+                .iter_bounds()
+                .zip(x.iter())
+                .map(|(bound, v)| match (bound, bound.repr(v)) {
+                    (BoundType::Categorical(_), Some(BoundRepr::Categorical((_, cat)))) => {
+                        match json {
+                            true => format!("\"{}\"", cat),
+                            false => cat,
+                        }
+                    }
+                    (BoundType::Boolean(_), Some(BoundRepr::Boolean((_, b)))) => b.to_string(),
+                    _ => num(v),
+                })
+                .collect(),
+            _ => x.iter().map(num).collect(),
+        }
+    }
+
+    fn json_point(&self, x: ArrayView1<f_>) -> String {
+        format!("[{}]", self.coords(x, true).join(", "))
+    }
+
+    fn json_points(&self, X: ArrayView2<f_>) -> String {
+        let cols: Vec<String> = X
+            .columns()
+            .into_iter()
+            .map(|x| self.json_point(x))
+            .collect();
+        format!("[{}]", cols.join(", "))
+    }
+
+    fn dim(&self) -> usize {
+        match &self.bounds {
+            Some(bounds) => bounds.dim(),
+            None => self.frames.first().map(|f| f.X.nrows()).unwrap_or(0),
+        }
+    }
+}
+
+fn json_array(x: ArrayView1<f_>) -> String {
+    let vals: Vec<String> = x
+        .iter()
+        .map(|v| match v.is_finite() {
+            true => v.to_string(),
+            false => "null".into(),
+        })
+        .collect();
+    format!("[{}]", vals.join(", "))
+}
+
+fn json_columns(X: ArrayView2<f_>) -> String {
+    let cols: Vec<String> = X.columns().into_iter().map(json_array).collect();
+    format!("[{}]", cols.join(", "))
+}
+
+fn json_rows(X: ArrayView2<f_>) -> String {
+    let rows: Vec<String> = X.rows().into_iter().map(json_array).collect();
+    format!("[{}]", rows.join(", "))
+}
+
+const SVG_SIZE: f_ = 500.0;
+
+fn svg_frame(frame: &Frame, bounds: &Array2<f_>) -> String {
+    let px_x = |x: f_| (x - bounds[(0, 0)]) / (bounds[(0, 1)] - bounds[(0, 0)]) * SVG_SIZE;
+    let px_y = |x: f_| (1.0 - (x - bounds[(1, 0)]) / (bounds[(1, 1)] - bounds[(1, 0)])) * SVG_SIZE;
+    let to_px = |x: ArrayView1<f_>| -> (f_, f_) { (px_x(x[0]), px_y(x[1])) };
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n",
+        SVG_SIZE
+    );
+    let _ = writeln!(
+        svg,
+        "<rect width=\"{0}\" height=\"{0}\" fill=\"white\" stroke=\"black\"/>",
+        SVG_SIZE
+    );
+
+    if let Some(grid) = &frame.grid {
+        let min = grid.mean.iter().copied().fold(f_::INFINITY, f_::min);
+        let max = grid.mean.iter().copied().fold(f_::NEG_INFINITY, f_::max);
+        let cols = cell_edges(grid.x1.iter().map(|x| px_x(*x)));
+        let rows = cell_edges(grid.x2.iter().map(|x| px_y(*x)));
+
+        for ((r, c), mean) in grid.mean.indexed_iter() {
+            let t = match max > min {
+                true => (mean - min) / (max - min),
+                false => 0.0,
+            };
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\"/>",
+                cols[c].0,
+                rows[r].0,
+                cols[c].1,
+                rows[r].1,
+                colour_map(t)
+            );
+        }
+    }
+
+    if let Some(tr) = &frame.trust_region {
+        let pts: Vec<String> = tr
+            .polygon
+            .columns()
+            .into_iter()
+            .map(|x| {
+                let (px, py) = to_px(x);
+                format!("{:.2},{:.2}", px, py)
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polygon points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\"/>",
+            pts.join(" ")
+        );
+    }
+
+    for x in frame.X.columns() {
+        let (px, py) = to_px(x);
+        let _ = writeln!(
+            svg,
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"3\" fill=\"white\" stroke=\"black\"/>",
+            px, py
+        );
+    }
+
+    for x in frame.suggestion.columns() {
+        let (px, py) = to_px(x);
+        let _ = writeln!(
+            svg,
+            "<path d=\"M {0:.2} {1:.2} l 10 10 m -10 0 l 10 -10\" transform=\"translate(-5 -5)\" stroke=\"red\" stroke-width=\"2\"/>",
+            px, py
+        );
+    }
+
+    let _ = writeln!(
+        svg,
+        "<text x=\"5\" y=\"15\" font-family=\"monospace\" font-size=\"12\">n = {}</text>",
+        frame.n_samples
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Start and width of the cells centred on the pixel coordinates `px`, which need not be evenly spaced.
+fn cell_edges(px: impl Iterator<Item = f_>) -> Vec<(f_, f_)> {
+    let px: Vec<f_> = px.collect();
+    let n = px.len();
+
+    (0..n)
+        .map(|i| {
+            let lo = match i {
+                0 => px[0] - (px[1] - px[0]) / 2.0,
+                _ => (px[i - 1] + px[i]) / 2.0,
+            };
+            let hi = match i == n - 1 {
+                true => px[i] + (px[i] - px[i - 1]) / 2.0,
+                false => (px[i] + px[i + 1]) / 2.0,
+            };
+            (lo.min(hi), (hi - lo).abs())
+        })
+        .collect()
+}
+
+/// Approximation of the viridis colour map for `t` in [0, 1].
+fn colour_map(t: f_) -> String {
+    const STOPS: [(f_, f_, f_); 5] = [
+        (68.0, 1.0, 84.0),
+        (59.0, 82.0, 139.0),
+        (33.0, 145.0, 140.0),
+        (94.0, 201.0, 98.0),
+        (253.0, 231.0, 37.0),
+    ];
+
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f_;
+    let i = (t.floor() as usize).min(STOPS.len() - 2);
+    let frac = t - i as f_;
+    let lerp = |a: f_, b: f_| (a + frac * (b - a)).round() as u8;
+
+    format!(
+        "rgb({},{},{})",
+        lerp(STOPS[i].0, STOPS[i + 1].0),
+        lerp(STOPS[i].1, STOPS[i + 1].1),
+        lerp(STOPS[i].2, STOPS[i + 1].2)
+    )
+}
@@ -0,0 +1,12 @@
+use crate::bounds::{BoundReprs, Bounds, Ready};
+
+#[cfg(feature = "derive")]
+pub use labcat_derive::SearchSpace;
+
+/// Struct with a field for every bound of a search space, converted to and from the
+/// representations of the points of the bounds.
+///
+/// Can be derived with the `derive` feature, see `labcat_derive::SearchSpace`.
+pub trait SearchSpace: From<BoundReprs> + Into<BoundReprs> {
+    fn bounds() -> Bounds<Ready>;
+}
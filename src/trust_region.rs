@@ -0,0 +1,196 @@
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+
+use crate::{
+    bounds::{Bounds, Ready},
+    f_,
+};
+
+/// Snapshot of the geometry of the trust region, expressed in the (transformed) space of the bounds.
+///
+/// The trust region is a hyperrectangle centred on `centre`, with sides aligned to the orthonormal
+/// columns of `axes` and half side lengths `half_lengths` along each axis.
+#[derive(Debug, Clone)]
+pub struct TrustRegion {
+    centre: Array1<f_>,
+    axes: Array2<f_>,
+    half_lengths: Array1<f_>,
+}
+
+impl TrustRegion {
+    pub fn new(centre: Array1<f_>, axes: Array2<f_>, half_lengths: Array1<f_>) -> TrustRegion {
+        if axes.nrows() != centre.len() || axes.ncols() != half_lengths.len() {
+            panic!("Trust region centre, axes and side lengths dims do not match!")
+        }
+
+        TrustRegion {
+            centre,
+            axes,
+            half_lengths,
+        }
+    }
+
+    /// Trust region restricted to the coordinates at `dims`, which must not be mixed with the
+    /// other coordinates by the axes.
+    pub fn select_dims(self, dims: &[usize]) -> TrustRegion {
+        TrustRegion::new(
+            self.centre.select(Axis(0), dims),
+            self.axes.select(Axis(0), dims).select(Axis(1), dims),
+            self.half_lengths.select(Axis(0), dims),
+        )
+    }
+
+    /// Trust region with a new coordinate `dim` at `value`, along which it has zero side length.
+    pub fn insert_flat_dim(self, dim: usize, value: f_) -> TrustRegion {
+        let d = self.dim() + 1;
+        let src = |i: usize| if i > dim { i - 1 } else { i };
+
+        let centre = Array1::from_shape_fn((d,), |i| match i == dim {
+            true => value,
+            false => self.centre[src(i)],
+        });
+        let axes = Array2::from_shape_fn((d, d), |(i, j)| match (i == dim, j == dim) {
+            (true, true) => 1.0,
+            (false, false) => self.axes[(src(i), src(j))],
+            _ => 0.0,
+        });
+        let half_lengths = Array1::from_shape_fn((d,), |i| match i == dim {
+            true => 0.0,
+            false => self.half_lengths[src(i)],
+        });
+
+        TrustRegion::new(centre, axes, half_lengths)
+    }
+
+    pub fn dim(&self) -> usize {
+        self.centre.len()
+    }
+
+    pub fn centre(&self) -> &Array1<f_> {
+        &self.centre
+    }
+
+    /// Principal axes of the trust region, stored as columns.
+    pub fn axes(&self) -> &Array2<f_> {
+        &self.axes
+    }
+
+    pub fn half_lengths(&self) -> &Array1<f_> {
+        &self.half_lengths
+    }
+
+    pub fn side_lengths(&self) -> Array1<f_> {
+        self.half_lengths.map(|l| 2.0 * l)
+    }
+
+    pub fn volume(&self) -> f_ {
+        self.side_lengths().product()
+    }
+
+    /// Extent of the trust region along each coordinate axis of the bounds.
+    pub fn extent(&self) -> Array1<f_> {
+        self.axes.map(|val| val.abs()).dot(&self.side_lengths())
+    }
+
+    /// Maps a point to the local coordinates of the trust region, where the trust region is `[-1, 1]^d`.
+    /// Along flat axes the local coordinate is 0 at the centre and infinite elsewhere.
+    pub fn to_local(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        let mut u = self.axes.t().dot(&(&x - &self.centre));
+        u.iter_mut()
+            .zip(self.half_lengths.iter())
+            .for_each(|(u, l)| {
+                *u = match (*l == 0.0, *u == 0.0) {
+                    (false, _) => *u / l,
+                    (true, true) => 0.0,
+                    (true, false) => f_::INFINITY.copysign(*u),
+                }
+            });
+        u
+    }
+
+    /// Maps local coordinates of the trust region back to a point.
+    pub fn from_local(&self, u: ArrayView1<f_>) -> Array1<f_> {
+        self.axes.dot(&(&u * &self.half_lengths)) + &self.centre
+    }
+
+    pub fn contains(&self, x: ArrayView1<f_>) -> bool {
+        self.to_local(x).iter().all(|u| u.abs() <= 1.0)
+    }
+
+    /// Projects a point onto the closest point inside the trust region.
+    pub fn project(&self, x: ArrayView1<f_>) -> Array1<f_> {
+        let u = self.to_local(x).map(|u| u.clamp(-1.0, 1.0));
+        self.from_local(u.view())
+    }
+
+    /// Vertices of the trust region as columns, ordered so that consecutive vertices in 2D trace
+    /// the boundary of the region.
+    pub fn corners(&self) -> Array2<f_> {
+        let d = self.dim();
+        let mut corners = Array2::zeros((d, 0));
+
+        for i in 0..(1usize << d) {
+            // Gray code ordering, so that consecutive vertices differ along a single axis
+            let gray = i ^ (i >> 1);
+            let u = Array1::from_shape_fn((d,), |j| match (gray >> j) & 1 {
+                1 => 1.0,
+                _ => -1.0,
+            });
+            corners
+                .push(Axis(1), self.from_local(u.view()).view())
+                .expect("push should never fail");
+        }
+
+        corners
+    }
+
+    /// Centre of the trust region in the original units of `bounds`.
+    pub fn centre_untransformed(&self, bounds: &Bounds<Ready>) -> Array1<f_> {
+        bounds.untransform(self.centre.view())
+    }
+
+    /// Vertices of the trust region as columns in the original units of `bounds`, see
+    /// [`TrustRegion::corners`].
+    pub fn corners_untransformed(&self, bounds: &Bounds<Ready>) -> Array2<f_> {
+        let mut corners = self.corners();
+        corners
+            .columns_mut()
+            .into_iter()
+            .for_each(|mut x| x.assign(&bounds.untransform(x.view())));
+        corners
+    }
+
+    /// Extent of the trust region along each coordinate axis in the original units of `bounds`.
+    pub fn extent_untransformed(&self, bounds: &Bounds<Ready>) -> Array1<f_> {
+        let half_extent = self.extent() / 2.0;
+        let lower = bounds.untransform((&self.centre - &half_extent).view());
+        let upper = bounds.untransform((&self.centre + &half_extent).view());
+        (upper - lower).mapv(f_::abs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, arr2};
+
+    fn flat_region() -> TrustRegion {
+        TrustRegion::new(arr1(&[0.0]), arr2(&[[1.0]]), arr1(&[0.5])).insert_flat_dim(1, 2.0)
+    }
+
+    #[test]
+    fn flat_dim_contains_centre() {
+        let tr = flat_region();
+
+        assert!(tr.contains(arr1(&[0.25, 2.0]).view()));
+        assert!(!tr.contains(arr1(&[0.25, 2.5]).view()));
+        assert_eq!(tr.to_local(arr1(&[0.25, 2.0]).view()), arr1(&[0.5, 0.0]));
+    }
+
+    #[test]
+    fn flat_dim_project() {
+        let tr = flat_region();
+
+        assert_eq!(tr.project(arr1(&[1.0, 3.0]).view()), arr1(&[0.5, 2.0]));
+        assert_eq!(tr.project(arr1(&[-0.25, 1.0]).view()), arr1(&[-0.25, 2.0]));
+    }
+}
@@ -0,0 +1,61 @@
+#![cfg(not(feature = "python"))] // the python bindings replace the builders used below
+
+use labcat::bound_types::{BoundRepr, BoundTrait, QuantizedContinuous};
+use labcat::{LABCAT, bounds::Bounds, f_};
+use ndarray::{Array1, Array2};
+
+#[allow(non_snake_case)]
+fn rosenbrock(X: &Array2<f_>) -> Array1<f_> {
+    X.columns()
+        .into_iter()
+        .map(|x| {
+            (0..x.len() - 1)
+                .map(|i| 100.0 * (x[i + 1] - x[i].powi(2)).powi(2) + (1.0 - x[i]).powi(2))
+                .sum()
+        })
+        .collect()
+}
+
+/// Short run of the demo on the Rosenbrock function, which must improve on the initial design.
+fn run_rosenbrock() {
+    let res = LABCAT::new(Bounds::new_continuous(2, 5.0, -5.0))
+        .build()
+        .set_target_fn(rosenbrock)
+        .max_samples(40)
+        .run();
+
+    assert_eq!(*res.n_samples(), 40);
+    assert!(res.min_y().is_finite());
+    assert!(
+        *res.min_y() < 10.0,
+        "best output {} after 40 samples",
+        res.min_y()
+    );
+}
+
+#[cfg(feature = "f64")]
+#[test]
+fn rosenbrock_f64() {
+    assert_eq!(std::mem::size_of::<f_>(), 8);
+    run_rosenbrock();
+}
+
+#[cfg(not(feature = "f64"))]
+#[test]
+fn rosenbrock_f32() {
+    assert_eq!(std::mem::size_of::<f_>(), 4);
+    run_rosenbrock();
+}
+
+#[test]
+fn quantized_steps_at_precision() {
+    let quant = QuantizedContinuous::new("q", 1.0, 0.0, 0.1);
+    assert_eq!(quant.range(), (0.0, quant.snap(&1.0)));
+    assert!((quant.range().1 - 1.0).abs() <= quant.tol());
+
+    for i in 0..=10 {
+        let val = i as f_ * 0.1;
+        let parsed = quant.parse(&BoundRepr::Continuous(("q".into(), val)));
+        assert!((parsed - val).abs() <= quant.tol());
+    }
+}
@@ -0,0 +1,161 @@
+#![cfg(all(feature = "derive", not(feature = "python")))]
+
+use labcat::bound_types::{BoundTrait, BoundType};
+use labcat::bounds::BoundReprs;
+use labcat::{LABCAT, f_, i_, search_space::SearchSpace};
+
+#[derive(Debug, Clone, PartialEq, SearchSpace)]
+struct Space {
+    #[labcat(range = 1e-4..1e-1, transform = "log")]
+    rate: f_,
+    #[labcat(range = 0.0..0.5, step = 0.1)]
+    dropout: f_,
+    #[labcat(range = 0.5..2.0)]
+    scale: f64,
+    #[labcat(range = 1..8)]
+    half_open: i64,
+    #[labcat(range = 1..=8)]
+    closed: i32,
+    #[labcat(range = 1..=64, transform = "log")]
+    width: usize,
+    #[labcat(levels = [16, 32.0, 64.0])]
+    batch: f_,
+    #[labcat(categories = ["adam", "sgd"])]
+    optimizer: String,
+    shuffle: bool,
+}
+
+fn bound(label: &str) -> BoundType {
+    Space::bounds()
+        .iter_bounds()
+        .find(|b| b.label() == label)
+        .unwrap_or_else(|| panic!("bound {} is missing", label))
+        .clone()
+}
+
+#[test]
+fn bounds_follow_the_fields() {
+    let labels: Vec<String> = Space::bounds()
+        .iter_bounds()
+        .map(|b| b.label().to_string())
+        .collect();
+    let fields = [
+        "rate",
+        "dropout",
+        "scale",
+        "half_open",
+        "closed",
+        "width",
+        "batch",
+        "optimizer",
+        "shuffle",
+    ];
+    assert_eq!(labels, fields);
+
+    match bound("rate") {
+        BoundType::Continuous(rate) => {
+            assert_eq!(rate.range(), (1e-4, 1e-1));
+            assert_eq!(rate.transform().name(), "log");
+        }
+        other => panic!("rate is {}", other),
+    }
+    match bound("dropout") {
+        BoundType::QuantizedContinuous(dropout) => {
+            assert_eq!(dropout.step(), 0.1);
+            assert!((dropout.range().1 - 0.5).abs() <= dropout.tol());
+        }
+        other => panic!("dropout is {}", other),
+    }
+    match bound("scale") {
+        BoundType::Continuous(scale) => assert_eq!(scale.transform().name(), "linear"),
+        other => panic!("scale is {}", other),
+    }
+    match bound("batch") {
+        BoundType::Ordinal(batch) => assert_eq!(batch.levels(), &vec![16.0, 32.0, 64.0]),
+        other => panic!("batch is {}", other),
+    }
+    match bound("optimizer") {
+        BoundType::Categorical(optimizer) => assert_eq!(optimizer.categories(), &["adam", "sgd"]),
+        other => panic!("optimizer is {}", other),
+    }
+    assert!(matches!(bound("shuffle"), BoundType::Boolean(_)));
+}
+
+#[test]
+fn half_open_int_ranges_exclude_the_end() {
+    let range = |label| match bound(label) {
+        BoundType::Discrete(disc) => disc.range(),
+        other => panic!("{} is {}", label, other),
+    };
+
+    assert_eq!(range("half_open"), (1 as i_, 7 as i_));
+    assert_eq!(range("closed"), (1 as i_, 8 as i_));
+    assert_eq!(range("width"), (1 as i_, 64 as i_));
+
+    match bound("width") {
+        BoundType::Discrete(width) => assert_eq!(width.transform().name(), "log"),
+        other => panic!("width is {}", other),
+    }
+}
+
+#[test]
+fn struct_round_trips_through_reprs() {
+    let x = Space {
+        rate: 0.01,
+        dropout: 0.2,
+        scale: 1.5,
+        half_open: 7,
+        closed: 8,
+        width: 32,
+        batch: 64.0,
+        optimizer: "sgd".into(),
+        shuffle: true,
+    };
+
+    let reprs: BoundReprs = x.clone().into();
+    assert_eq!(reprs.get_i64("closed"), Some(8));
+    assert_eq!(reprs.get_str("optimizer"), Some("sgd"));
+    assert_eq!(Space::from(reprs.clone()), x);
+
+    // The reprs parse back onto the same point of the bounds
+    let bounds = Space::bounds();
+    let point = bounds.parse(reprs);
+    let parsed = Space::from(
+        bounds
+            .repr(point.view())
+            .expect("point is inside the bounds"),
+    );
+    assert_eq!(parsed.half_open, 7);
+    assert_eq!(parsed.optimizer, "sgd");
+    assert!((parsed.rate - 0.01).abs() < 1e-6);
+}
+
+fn objective(x: Space) -> f_ {
+    (x.rate.log10() + 2.0).powi(2) + (x.closed as f_ - 3.0).powi(2) + x.dropout
+}
+
+#[test]
+fn typed_target_fn() {
+    let res = LABCAT::new_typed::<Space>()
+        .build()
+        .set_target_fn_typed(objective)
+        .max_samples(30)
+        .run();
+
+    assert_eq!(*res.n_samples(), 30);
+    assert!(res.min_y().is_finite());
+}
+
+#[test]
+#[should_panic(expected = "Bounds of the search space do not match")]
+fn typed_target_fn_checks_the_bounds() {
+    LABCAT::new(labcat::bounds::Bounds::new_continuous(2, 1.0, 0.0))
+        .build()
+        .set_target_fn_typed(objective);
+}
+
+#[test]
+fn compile_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
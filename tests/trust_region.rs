@@ -0,0 +1,46 @@
+#![cfg(not(feature = "python"))] // the python bindings replace the builders used below
+
+use labcat::bounds_transforms::BoundTransform;
+use labcat::recorder::Recorder;
+use labcat::{LABCAT, bounds::Bounds, f_};
+use ndarray::{Array1, Array2};
+
+/// Quadratic with its minimum at `x = 50` on a log-transformed bound and `z = 0` on a linear one.
+#[allow(non_snake_case)]
+fn log_quadratic(X: &Array2<f_>) -> Array1<f_> {
+    X.columns()
+        .into_iter()
+        .map(|x| (x[0].exp() - 50.0).powi(2) / 100.0 + x[1].powi(2))
+        .collect()
+}
+
+#[test]
+fn x_tol_in_original_units() {
+    let bounds = Bounds::new()
+        .add_continuous_with_transform("x", 100.0, 0.01, BoundTransform::Log)
+        .add_continuous("z", 1.0, -1.0)
+        .build();
+
+    let tol = 1.0;
+    let res = LABCAT::new(bounds)
+        .record(Recorder::new())
+        .build()
+        .set_target_fn(log_quadratic)
+        .x_tol(tol)
+        .max_samples(300)
+        .run();
+    assert_eq!(res.reason(), "Trust region collapsed!");
+
+    // The last frame holds the trust region that triggered the termination
+    let frame = res.recording().unwrap().frames().last().unwrap().clone();
+    let x_extent = frame.trust_region.unwrap().extent[0];
+    assert!(x_extent < tol, "extent of x {} in original units", x_extent);
+
+    // Near x = 50 a unit in the original space is far smaller than a unit in log space
+    let log_extent = frame.trust_region_transformed.unwrap().extent()[0];
+    assert!(
+        log_extent < tol / 10.0,
+        "extent of x {} in log units",
+        log_extent
+    );
+}
@@ -0,0 +1,8 @@
+use labcat::search_space::SearchSpace;
+
+#[derive(SearchSpace)]
+struct Space {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use labcat::search_space::SearchSpace;
+
+#[derive(SearchSpace)]
+struct Space {
+    #[labcat(transform = "log")]
+    x: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use labcat::search_space::SearchSpace;
+
+#[derive(SearchSpace)]
+enum Space {
+    A,
+    B,
+}
+
+fn main() {}
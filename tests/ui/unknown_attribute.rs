@@ -0,0 +1,9 @@
+use labcat::search_space::SearchSpace;
+
+#[derive(SearchSpace)]
+struct Space {
+    #[labcat(range = 0.0..1.0, prior = "normal")]
+    x: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use labcat::search_space::SearchSpace;
+
+#[derive(SearchSpace)]
+struct Space {
+    #[labcat(range = 0.1..1.0, transform = "logarithmic")]
+    x: f64,
+}
+
+fn main() {}